@@ -0,0 +1,319 @@
+//! Adapters stacking behaviour on top of an [`Io`].
+//!
+//! ```
+//! use aoc::{adapt::IoExt, IntCode, MemIo};
+//!
+//! let mut log = Vec::new();
+//! let io = MemIo::new(vec![20])
+//!     .map_input(|it| it + 1)
+//!     .map_output(|it| it * 2)
+//!     .logged("cpu", &mut log);
+//! let mut cpu = IntCode::new(io, vec![3, 0, 4, 0, 99]);
+//! cpu.run().unwrap();
+//! drop(cpu);
+//! assert_eq!(String::from_utf8(log).unwrap(), "cpu: in 21\ncpu: out 21\n");
+//! ```
+//!
+//! The outermost adapter is the one the machine talks to, so the log above
+//! shows the values before the output is doubled.
+//!
+//! [`Framed`] groups outputs into fixed-size frames, like day13's `x, y,
+//! tile` triples. [`IterIo`] and [`Reactive`] build an `Io` out of closures.
+use std::io::Write;
+
+use crate::{Io, Result};
+
+impl<T: Io + ?Sized> Io for &mut T {
+    fn read(&mut self) -> Result<Option<i64>> {
+        (**self).read()
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        (**self).write(value)
+    }
+}
+
+impl<T: Io + ?Sized> Io for Box<T> {
+    fn read(&mut self) -> Result<Option<i64>> {
+        (**self).read()
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        (**self).write(value)
+    }
+}
+
+pub trait IoExt: Io + Sized {
+    /// Copies every output to `other` too.
+    fn tee<B: Io>(self, other: B) -> Tee<Self, B> {
+        Tee { io: self, other }
+    }
+    /// Applies `f` to every value read.
+    fn map_input<F: FnMut(i64) -> i64>(self, f: F) -> MapInput<Self, F> {
+        MapInput { io: self, f }
+    }
+    /// Applies `f` to every value before writing it.
+    fn map_output<F: FnMut(i64) -> i64>(self, f: F) -> MapOutput<Self, F> {
+        MapOutput { io: self, f }
+    }
+    /// Writes a line to `out` for every value read or written.
+    fn logged<W: Write>(self, label: &str, out: W) -> Logged<Self, W> {
+        Logged {
+            io: self,
+            label: label.to_string(),
+            out,
+        }
+    }
+}
+
+impl<T: Io> IoExt for T {}
+
+pub struct Tee<A, B> {
+    pub io: A,
+    pub other: B,
+}
+
+impl<A: Io, B: Io> Io for Tee<A, B> {
+    fn read(&mut self) -> Result<Option<i64>> {
+        self.io.read()
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        self.io.write(value)?;
+        self.other.write(value)
+    }
+}
+
+pub struct MapInput<IO, F> {
+    pub io: IO,
+    f: F,
+}
+
+impl<IO: Io, F: FnMut(i64) -> i64> Io for MapInput<IO, F> {
+    fn read(&mut self) -> Result<Option<i64>> {
+        Ok(self.io.read()?.map(&mut self.f))
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        self.io.write(value)
+    }
+}
+
+pub struct MapOutput<IO, F> {
+    pub io: IO,
+    f: F,
+}
+
+impl<IO: Io, F: FnMut(i64) -> i64> Io for MapOutput<IO, F> {
+    fn read(&mut self) -> Result<Option<i64>> {
+        self.io.read()
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        self.io.write((self.f)(value))
+    }
+}
+
+pub struct Logged<IO, W> {
+    pub io: IO,
+    label: String,
+    out: W,
+}
+
+impl<IO: Io, W: Write> Io for Logged<IO, W> {
+    fn read(&mut self) -> Result<Option<i64>> {
+        let res = self.io.read()?;
+        match res {
+            Some(value) => writeln!(self.out, "{}: in {}", self.label, value)?,
+            None => writeln!(self.out, "{}: end of input", self.label)?,
+        }
+        Ok(res)
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        writeln!(self.out, "{}: out {}", self.label, value)?;
+        self.io.write(value)
+    }
+}
+
+/// The inside of a [`Framed`]: inputs are read one at a time, outputs
+/// arrive as whole frames.
+pub trait FrameIo {
+    fn read(&mut self) -> Result<Option<i64>>;
+    fn write_frame(&mut self, frame: &[i64]) -> Result<()>;
+}
+
+pub struct Framed<T> {
+    pub inner: T,
+    size: usize,
+    buf: Vec<i64>,
+}
+
+impl<T: FrameIo> Framed<T> {
+    pub fn new(inner: T, size: usize) -> Framed<T> {
+        assert!(size > 0, "empty frames");
+        Framed {
+            inner,
+            size,
+            buf: Vec::with_capacity(size),
+        }
+    }
+    /// Outputs of the incomplete frame.
+    pub fn pending(&self) -> &[i64] {
+        &self.buf
+    }
+}
+
+impl<T: FrameIo> Io for Framed<T> {
+    fn read(&mut self) -> Result<Option<i64>> {
+        self.inner.read()
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        self.buf.push(value);
+        if self.buf.len() == self.size {
+            let res = self.inner.write_frame(&self.buf);
+            self.buf.clear();
+            res?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads from an iterator, and passes outputs to a closure.
+pub struct IterIo<I, F> {
+    input: I,
+    output: F,
+}
+
+impl<I: Iterator<Item = i64>, F: FnMut(i64)> IterIo<I, F> {
+    pub fn new(input: impl IntoIterator<IntoIter = I>, output: F) -> IterIo<I, F> {
+        IterIo {
+            input: input.into_iter(),
+            output,
+        }
+    }
+}
+
+impl<I: Iterator<Item = i64>, F: FnMut(i64)> Io for IterIo<I, F> {
+    fn read(&mut self) -> Result<Option<i64>> {
+        Ok(self.input.next())
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        (self.output)(value);
+        Ok(())
+    }
+}
+
+/// Computes every input from the outputs so far, `None` ends the input.
+pub struct Reactive<F> {
+    outputs: Vec<i64>,
+    f: F,
+}
+
+impl<F: FnMut(&[i64]) -> Option<i64>> Reactive<F> {
+    pub fn new(f: F) -> Reactive<F> {
+        Reactive {
+            outputs: Vec::new(),
+            f,
+        }
+    }
+    pub fn outputs(&self) -> &[i64] {
+        &self.outputs
+    }
+    pub fn into_outputs(self) -> Vec<i64> {
+        self.outputs
+    }
+}
+
+impl<F: FnMut(&[i64]) -> Option<i64>> Io for Reactive<F> {
+    fn read(&mut self) -> Result<Option<i64>> {
+        Ok((self.f)(&self.outputs))
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        self.outputs.push(value);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_adapt() {
+    use crate::{IntCode, MemIo};
+
+    // Reads two values, outputs their sum and product.
+    let prog = vec![
+        3, 17, 3, 18, 1, 17, 18, 19, 4, 19, 2, 17, 18, 19, 4, 19, 99, 0, 0, 0,
+    ];
+    let mut copy = MemIo::new(vec![]);
+    let io = MemIo::new(vec![3, 4]).tee(&mut copy);
+    let mut cpu = IntCode::new(io, prog.clone());
+    cpu.run().unwrap();
+    assert_eq!(cpu.io.io.into_output(), vec![7, 12]);
+    assert_eq!(copy.into_output(), vec![7, 12]);
+
+    let mut copy = MemIo::new(vec![]);
+    let io = MemIo::new(vec![3, 4]).map_input(|it| -it).tee(&mut copy);
+    let mut io: Box<dyn Io + '_> = Box::new(io);
+    IntCode::new(&mut io, prog.clone()).run().unwrap();
+    IntCode::new(io, vec![104, 5, 99]).run().unwrap();
+    assert_eq!(copy.into_output(), vec![-7, 12, 5]);
+
+    struct Points {
+        inputs: Vec<i64>,
+        points: Vec<(i64, i64)>,
+    }
+    impl FrameIo for Points {
+        fn read(&mut self) -> Result<Option<i64>> {
+            Ok(self.inputs.pop())
+        }
+        fn write_frame(&mut self, frame: &[i64]) -> Result<()> {
+            self.points.push((frame[0], frame[1]));
+            Ok(())
+        }
+    }
+    let points = Points {
+        inputs: vec![5, 2],
+        points: vec![],
+    };
+    let mut io = Framed::new(points, 2);
+    IntCode::new(&mut io, prog.clone()).run().unwrap();
+    assert_eq!(io.inner.points, vec![(7, 10)]);
+    assert!(io.pending().is_empty());
+    IntCode::new(&mut io, vec![104, 1, 99]).run().unwrap();
+    assert_eq!(io.pending(), &[1]);
+}
+
+#[test]
+fn test_fn_io() {
+    use crate::{IntCode, Status};
+
+    // Doubles its inputs until it reads 0.
+    let prog = crate::asm::assemble(
+        "
+        loop:   in -> [x]
+                jf [x], #end
+                mul [x], #2 -> [x]
+                out [x]
+                jt #1, #loop
+        end:    hlt
+        x:      .data 0
+        ",
+    )
+    .unwrap();
+
+    let mut output = Vec::new();
+    let io = IterIo::new(vec![1, 2, 3, 0], |it| output.push(it));
+    IntCode::new(io, prog.clone()).run().unwrap();
+    assert_eq!(output, vec![2, 4, 6]);
+
+    let mut sum = 0;
+    let io = IterIo::new((1..).take(3), |it| sum += it);
+    let mut cpu = IntCode::new(io, prog.clone());
+    assert_eq!(cpu.run().unwrap(), Status::AwaitingInput);
+    assert_eq!(cpu.ip(), 0);
+    drop(cpu);
+    assert_eq!(sum, 12);
+
+    // Feeds back one more than the last output.
+    let io = Reactive::new(|outputs: &[i64]| match outputs {
+        [] => Some(1),
+        [.., last] if outputs.len() < 4 => Some(last + 1),
+        _ => Some(0),
+    });
+    let mut cpu = IntCode::new(io, prog);
+    cpu.run().unwrap();
+    assert_eq!(cpu.io.into_outputs(), vec![2, 6, 14, 30]);
+}
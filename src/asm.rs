@@ -0,0 +1,424 @@
+//! Assembler for the textual syntax printed by the disassembler.
+//!
+//! ```text
+//!         in -> [n]           ; comments start with a semicolon
+//! loop:   out [n]
+//!         add [n], #-1 -> [n]
+//!         jt [n], #loop
+//!         hlt
+//! n:      .data 0
+//! ```
+//!
+//! Operands are `#imm`, `[addr]` or `[rb+off]`, where numbers can be replaced
+//! by labels, optionally with an offset (`[buf+2]`).
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug)]
+pub struct AsmError {
+    pub line: usize,
+    pub col: usize,
+    pub msg: String,
+}
+
+impl std::error::Error for AsmError {}
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.msg)
+    }
+}
+
+pub fn assemble(text: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut words = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let mut p = Parser::new(idx + 1, line)?;
+        p.line(&mut labels, &mut words)?;
+    }
+
+    let mut res = Vec::with_capacity(words.len());
+    for word in words {
+        let value = match word {
+            Word::Lit(it) => it,
+            Word::Label { name, offset, pos } => match labels.get(&name) {
+                Some(&addr) => addr + offset,
+                None => Err(pos.error(format!("undefined label: {}", name)))?,
+            },
+        };
+        res.push(value);
+    }
+    Ok(res)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pos {
+    line: usize,
+    col: usize,
+}
+
+impl Pos {
+    fn error(self, msg: impl Into<String>) -> AsmError {
+        AsmError {
+            line: self.line,
+            col: self.col,
+            msg: msg.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Punct(char),
+    Arrow,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(it) => write!(f, "`{}`", it),
+            Token::Int(it) => write!(f, "`{}`", it),
+            Token::Punct(it) => write!(f, "`{}`", it),
+            Token::Arrow => write!(f, "`->`"),
+        }
+    }
+}
+
+fn tokenize(line_no: usize, line: &str) -> Result<Vec<(Pos, Token)>, AsmError> {
+    let mut res = Vec::new();
+    let chars = line.char_indices().collect::<Vec<_>>();
+    let mut i = 0;
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        let pos = Pos {
+            line: line_no,
+            col: line[..start].chars().count() + 1,
+        };
+        let word_end = |mut j: usize, pred: fn(char) -> bool| {
+            while j < chars.len() && pred(chars[j].1) {
+                j += 1;
+            }
+            j
+        };
+        let end_byte = |j: usize| chars.get(j).map_or(line.len(), |it| it.0);
+        let token = match c {
+            ';' => break,
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '-' if chars.get(i + 1).map(|it| it.1) == Some('>') => {
+                i += 2;
+                Token::Arrow
+            }
+            '-' | '0'..='9' => {
+                let j = word_end(i + 1, |c| c.is_ascii_digit());
+                let text = &line[start..end_byte(j)];
+                i = j;
+                match text.parse() {
+                    Ok(it) => Token::Int(it),
+                    // A lone `-` is an offset sign, as in `[rb-1]`.
+                    Err(_) if text == "-" => Token::Punct('-'),
+                    Err(_) => Err(pos.error(format!("invalid number: {}", text)))?,
+                }
+            }
+            '.' | '_' | 'a'..='z' | 'A'..='Z' => {
+                let j = word_end(i + 1, |c| c.is_alphanumeric() || c == '_');
+                let text = &line[start..end_byte(j)];
+                i = j;
+                Token::Ident(text.to_string())
+            }
+            ':' | ',' | '#' | '[' | ']' | '+' => {
+                i += 1;
+                Token::Punct(c)
+            }
+            _ => Err(pos.error(format!("unexpected character: {:?}", c)))?,
+        };
+        res.push((pos, token));
+    }
+    Ok(res)
+}
+
+#[derive(Debug)]
+enum Word {
+    Lit(i64),
+    Label { name: String, offset: i64, pos: Pos },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Positional = 0,
+    Immediate = 1,
+    Relative = 2,
+}
+
+struct Parser {
+    tokens: Vec<(Pos, Token)>,
+    eol: Pos,
+    idx: usize,
+}
+
+impl Parser {
+    fn new(line_no: usize, line: &str) -> Result<Parser, AsmError> {
+        let tokens = tokenize(line_no, line)?;
+        let eol = Pos {
+            line: line_no,
+            col: line.chars().count() + 1,
+        };
+        Ok(Parser {
+            tokens,
+            eol,
+            idx: 0,
+        })
+    }
+
+    fn pos(&self) -> Pos {
+        self.tokens.get(self.idx).map_or(self.eol, |it| it.0)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.idx).map(|it| &it.1)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let res = self.peek().cloned();
+        self.idx += 1;
+        res
+    }
+
+    fn unexpected(&self, expected: &str) -> AsmError {
+        let found = match self.peek() {
+            Some(it) => it.to_string(),
+            None => "end of line".to_string(),
+        };
+        self.pos()
+            .error(format!("expected {}, found {}", expected, found))
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        if self.peek() == Some(&token) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), AsmError> {
+        if !self.eat(token.clone()) {
+            Err(self.unexpected(&token.to_string()))?
+        }
+        Ok(())
+    }
+
+    fn line(
+        &mut self,
+        labels: &mut HashMap<String, i64>,
+        words: &mut Vec<Word>,
+    ) -> Result<(), AsmError> {
+        let pos = self.pos();
+        let mnemonic = match self.bump() {
+            None => return Ok(()),
+            Some(Token::Ident(it)) => it,
+            Some(_) => Err(pos.error("expected label, mnemonic or directive"))?,
+        };
+        if self.eat(Token::Punct(':')) {
+            if labels
+                .insert(mnemonic.clone(), words.len() as i64)
+                .is_some()
+            {
+                Err(pos.error(format!("duplicate label: {}", mnemonic)))?
+            }
+            return self.line(labels, words);
+        }
+
+        let op_code = match mnemonic.as_str() {
+            ".data" => {
+                loop {
+                    words.push(self.expr()?);
+                    if !self.eat(Token::Punct(',')) {
+                        break;
+                    }
+                }
+                return self.finish();
+            }
+            "add" => 1,
+            "mul" => 2,
+            "in" => 3,
+            "out" => 4,
+            "jt" => 5,
+            "jf" => 6,
+            "lt" => 7,
+            "eq" => 8,
+            "arb" => 9,
+            "hlt" => 99,
+            _ => Err(pos.error(format!("unknown mnemonic: {}", mnemonic)))?,
+        };
+
+        let mut args = Vec::new();
+        match op_code {
+            1 | 2 | 7 | 8 => {
+                args.push(self.operand()?);
+                self.expect(Token::Punct(','))?;
+                args.push(self.operand()?);
+                self.expect(Token::Arrow)?;
+                args.push(self.dst()?);
+            }
+            3 => {
+                self.expect(Token::Arrow)?;
+                args.push(self.dst()?);
+            }
+            4 | 9 => args.push(self.operand()?),
+            5 | 6 => {
+                args.push(self.operand()?);
+                self.expect(Token::Punct(','))?;
+                args.push(self.operand()?);
+            }
+            99 => (),
+            _ => unreachable!(),
+        }
+        self.finish()?;
+
+        let modes = args
+            .iter()
+            .rev()
+            .fold(0, |acc, (mode, _)| acc * 10 + *mode as i64);
+        words.push(Word::Lit(modes * 100 + op_code));
+        words.extend(args.into_iter().map(|(_, word)| word));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), AsmError> {
+        if self.peek().is_some() {
+            Err(self.unexpected("end of line"))?
+        }
+        Ok(())
+    }
+
+    fn dst(&mut self) -> Result<(Mode, Word), AsmError> {
+        let pos = self.pos();
+        let res = self.operand()?;
+        if res.0 == Mode::Immediate {
+            Err(pos.error("destination can't be immediate"))?
+        }
+        Ok(res)
+    }
+
+    fn operand(&mut self) -> Result<(Mode, Word), AsmError> {
+        if self.eat(Token::Punct('#')) {
+            return Ok((Mode::Immediate, self.expr()?));
+        }
+        if !self.eat(Token::Punct('[')) {
+            Err(self.unexpected("operand"))?
+        }
+        let res = if self.eat(Token::Ident("rb".to_string())) {
+            let word = match self.peek() {
+                Some(Token::Punct(']')) => Word::Lit(0),
+                Some(Token::Int(it)) if *it < 0 => self.expr()?,
+                Some(Token::Punct('-')) => {
+                    self.bump();
+                    Word::Lit(-self.int()?)
+                }
+                _ => {
+                    self.expect(Token::Punct('+'))?;
+                    self.expr()?
+                }
+            };
+            (Mode::Relative, word)
+        } else {
+            (Mode::Positional, self.expr()?)
+        };
+        self.expect(Token::Punct(']'))?;
+        Ok(res)
+    }
+
+    fn expr(&mut self) -> Result<Word, AsmError> {
+        let pos = self.pos();
+        match self.bump() {
+            Some(Token::Int(it)) => Ok(Word::Lit(it)),
+            Some(Token::Ident(name)) => {
+                let offset = match self.peek() {
+                    Some(Token::Punct('+')) => {
+                        self.bump();
+                        self.int()?
+                    }
+                    Some(Token::Int(it)) if *it < 0 => self.int()?,
+                    Some(Token::Punct('-')) => {
+                        self.bump();
+                        -self.int()?
+                    }
+                    _ => 0,
+                };
+                Ok(Word::Label { name, offset, pos })
+            }
+            _ => {
+                self.idx -= 1;
+                Err(self.unexpected("number or label"))
+            }
+        }
+    }
+
+    fn int(&mut self) -> Result<i64, AsmError> {
+        match self.peek() {
+            Some(&Token::Int(it)) => {
+                self.bump();
+                Ok(it)
+            }
+            _ => Err(self.unexpected("number")),
+        }
+    }
+}
+
+#[test]
+fn test_assemble() {
+    use crate::{disasm, IntCode, MemIo};
+
+    let prog = assemble(
+        "
+        ; counts down from the input
+                in -> [n]
+        loop:   out [n]
+                add [n], #-1 -> [n]
+                jt [n], #loop
+                hlt
+        n:      .data 0
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        prog,
+        vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0]
+    );
+
+    let mut mem = prog.clone();
+    let mut cpu = IntCode::new(MemIo::new(vec![3]), &mut mem);
+    cpu.run().unwrap();
+    assert_eq!(cpu.io.into_output(), vec![3, 2, 1]);
+
+    // The disassembler's output assembles back to the same image.
+    let prog = vec![
+        109, 19, 21201, -1, 7, 0, 204, -19, 1206, 0, 3, 2108, 5, 2, 1, 99,
+    ];
+    let text = disasm::disassemble(&prog)
+        .iter()
+        .map(|it| it.to_string().split(": ").nth(1).unwrap().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert_eq!(assemble(&text).unwrap(), prog);
+}
+
+#[test]
+fn test_assemble_errors() {
+    fn check(text: &str, expected: &str) {
+        let err = assemble(text).unwrap_err();
+        assert_eq!(err.to_string(), expected);
+    }
+
+    check("add #1, #2 -> #3", "1:15: destination can't be immediate");
+    check("hlt\n  jt [x], #1", "2:7: undefined label: x");
+    check("a: hlt\na: hlt", "2:1: duplicate label: a");
+    check("  mov [1], [2]", "1:3: unknown mnemonic: mov");
+    check("out [rb+1", "1:10: expected `]`, found end of line");
+    check("out #1 #2", "1:8: expected end of line, found `#`");
+    check("in [1]", "1:4: expected `->`, found `[`");
+}
@@ -0,0 +1,62 @@
+//! Compares the plain interpreter with the decode cache.
+//!
+//! Usage: `bench [program.in [runs]]`, defaulting to the day9 BOOST program,
+//! which is run in sensor boost mode (input 2).
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use aoc::{parse_memory, IntCode, MemIo, Result};
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| "./input/day9.in".to_string());
+    let runs = match args.next() {
+        Some(it) => it.parse()?,
+        None => 10,
+    };
+    let prog = parse_memory(&fs::read_to_string(path)?)?;
+
+    let (plain, expected) = bench(runs, || IntCode::new(MemIo::new(vec![2]), prog.clone()))?;
+    let (cached, output) = bench(runs, || {
+        IntCode::new(MemIo::new(vec![2]), prog.clone()).with_cache()
+    })?;
+    if output != expected {
+        Err(format!("outputs differ: {:?} vs {:?}", expected, output))?
+    }
+    let mut proto = IntCode::new(MemIo::new(vec![2]), prog.clone());
+    proto.predecode(0..prog.len() as i64);
+    let (predecoded, output) = bench(runs, || proto.clone())?;
+    if output != expected {
+        Err(format!("outputs differ: {:?} vs {:?}", expected, output))?
+    }
+
+    println!("output:     {:?}", expected);
+    println!("plain:      {:?}", plain);
+    println!(
+        "cached:     {:?} ({:.2}x)",
+        cached,
+        plain.as_secs_f64() / cached.as_secs_f64()
+    );
+    println!(
+        "predecoded: {:?} ({:.2}x)",
+        predecoded,
+        plain.as_secs_f64() / predecoded.as_secs_f64()
+    );
+    Ok(())
+}
+
+/// Returns the fastest of `runs` runs, and the output of the last one.
+fn bench(runs: usize, mut make: impl FnMut() -> IntCode<MemIo>) -> Result<(Duration, Vec<i64>)> {
+    let mut best = Duration::MAX;
+    let mut output = Vec::new();
+    for _ in 0..runs {
+        let mut cpu = make();
+        let start = Instant::now();
+        cpu.run()?;
+        best = best.min(start.elapsed());
+        output = cpu.io.into_output();
+    }
+    Ok((best, output))
+}
@@ -0,0 +1,24 @@
+//! Compiles a program in the tiny language of `aoc::lang` to Intcode.
+//!
+//! Usage: `compile [--asm] program.ic > program.in`.
+use std::fs;
+
+use aoc::{lang, Result};
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+    let asm = args.first().map(|it| it == "--asm").unwrap_or(false);
+    if asm {
+        args.remove(0);
+    }
+    let path = args.pop().ok_or("usage: compile [--asm] program.ic")?;
+    let text = fs::read_to_string(&path)?;
+    if asm {
+        print!("{}", lang::compile_to_asm(&text)?);
+    } else {
+        let mem = lang::compile(&text)?;
+        let mem = mem.iter().map(|it| it.to_string()).collect::<Vec<_>>();
+        println!("{}", mem.join(","));
+    }
+    Ok(())
+}
@@ -3,14 +3,11 @@ use std::{
     convert::{TryFrom, TryInto},
 };
 
-use aoc::{
-    extend_memory, parse_memory, read_stdin_to_string, Direction, IntCode, Point, Result, SlotIo,
-};
+use aoc::{parse_memory, read_stdin_to_string, Direction, Event, IntCode, Point, Result};
 
 fn main() -> Result<()> {
     let input = read_stdin_to_string()?;
-    let mut prog = parse_memory(&input)?;
-    extend_memory(&mut prog);
+    let prog = parse_memory(&input)?;
 
     let painted = run(prog)?;
     let min_x = painted.keys().map(|p| p.1).min().unwrap();
@@ -69,9 +66,7 @@ impl TryFrom<i64> for Color {
 }
 
 fn run(mut prog: Vec<i64>) -> Result<HashMap<Point, Color>> {
-    let slot = SlotIo::default();
-    let io = &slot;
-    let mut computer = IntCode::new(io, &mut prog);
+    let mut computer = IntCode::new((), &mut prog);
 
     let mut robot = Robot {
         pos: Point(0, 0),
@@ -80,35 +75,27 @@ fn run(mut prog: Vec<i64>) -> Result<HashMap<Point, Color>> {
     };
     robot.painted.insert(Point(0, 0), Color::White);
 
-    'outer: loop {
+    loop {
         let color = robot
             .painted
             .get(&robot.pos)
             .copied()
             .unwrap_or(Color::Black);
-        slot.set(color as i64);
-
-        while !slot.clear_read() {
-            if !computer.step()? {
-                break 'outer;
-            }
+        match computer.run_until_event()? {
+            Event::NeedsInput => computer.provide_input(color.into())?,
+            Event::Halted => break,
+            Event::Output(it) => Err(format!("unexpected output {}", it))?,
         }
 
-        while !slot.clear_write() {
-            if !computer.step()? {
-                break 'outer;
-            }
-        }
-        let color: Color = slot.get().try_into().map_err(|()| "bad color")?;
+        let (color, turn) = match (computer.run_until_event()?, computer.run_until_event()?) {
+            (Event::Output(color), Event::Output(turn)) => (color, turn),
+            events => Err(format!("expected color and turn, got {:?}", events))?,
+        };
+        let color: Color = color.try_into().map_err(|()| "bad color")?;
         let pos = robot.pos;
         robot.painted.insert(pos, color);
 
-        while !slot.clear_write() {
-            if !computer.step()? {
-                break 'outer;
-            }
-        }
-        robot.dir = match slot.get() {
+        robot.dir = match turn {
             0 => robot.dir.turn_left(),
             1 => robot.dir.turn_right(),
             it => Err(format!("unknown direction {}", it))?,
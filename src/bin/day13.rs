@@ -1,48 +1,48 @@
 use std::{cmp::Ordering, fs, io};
 
-use aoc::{parse_memory, IntCode, Io, Result};
+use aoc::{
+    adapt::{FrameIo, Framed},
+    parse_memory, IntCode, Result,
+};
 
 fn main() -> Result<()> {
     let mem = fs::read_to_string("./input/day13.in")?;
     let mut mem = parse_memory(&mem)?;
     mem[0] = 2;
-    let mut io = ConsoleIo {
-        buf: vec![],
+    let io = ConsoleIo {
         display: vec![vec![0; 40]; 21],
         score: 0,
         human: false,
         cnt: 0,
     };
-    let mut computer = IntCode::new(io, &mut mem);
+    let mut computer = IntCode::new(Framed::new(io, 3), &mut mem);
     computer.run()?;
-    let mut io = computer.io;
-    update_display(&mut io.score, &mut io.display, &io.buf);
+    let io = computer.io.inner;
     print_display(io.score, &io.display);
     Ok(())
 }
 
 struct ConsoleIo {
-    buf: Vec<i64>,
     display: Vec<Vec<i64>>,
     score: i64,
     human: bool,
     cnt: u64,
 }
 
-impl Io for ConsoleIo {
-    fn read(&mut self) -> Result<i64> {
-        update_display(&mut self.score, &mut self.display, &self.buf);
+impl FrameIo for ConsoleIo {
+    fn read(&mut self) -> Result<Option<i64>> {
         if self.cnt % 100 == 0 {
             print_display(self.score, &self.display);
         }
         self.cnt += 1;
-        self.buf.clear();
 
         let res = if self.human {
             let mut buf = String::new();
             loop {
                 buf.clear();
-                io::stdin().read_line(&mut buf)?;
+                if io::stdin().read_line(&mut buf)? == 0 {
+                    return Ok(None);
+                }
                 break match buf.trim() {
                     "j" => -1,
                     "k" => 1,
@@ -70,27 +70,16 @@ impl Io for ConsoleIo {
             }
         };
 
-        Ok(res)
+        Ok(Some(res))
     }
-    fn write(&mut self, value: i64) -> Result<()> {
-        self.buf.push(value);
-        Ok(())
-    }
-}
-
-fn update_display(score: &mut i64, display: &mut Vec<Vec<i64>>, buf: &[i64]) {
-    let mut chunks = buf.chunks_exact(3);
-    for tile in &mut chunks {
-        let x = tile[0];
-        let y = tile[1];
-        let t = tile[2];
-        if x == -1 {
-            *score = t;
-            continue;
+    fn write_frame(&mut self, frame: &[i64]) -> Result<()> {
+        match *frame {
+            [-1, _, score] => self.score = score,
+            [x, y, t] => self.display[y as usize][x as usize] = t,
+            _ => unreachable!(),
         }
-        display[y as usize][x as usize] = t;
+        Ok(())
     }
-    assert!(chunks.remainder().is_empty());
 }
 
 fn print_display(score: i64, display: &Vec<Vec<i64>>) {
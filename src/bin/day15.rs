@@ -1,14 +1,14 @@
 use std::fs;
 
-use aoc::{parse_memory, Board, Direction, IntCode, Point, Result, StepIo};
+use aoc::{parse_memory, Board, Direction, Event, IntCode, Point, Result};
 
 fn main() -> Result<()> {
     let prog = fs::read_to_string("./input/day15.in")?;
-    let mut prog = parse_memory(&prog)?;
+    let prog = parse_memory(&prog)?;
 
-    let cpu = IntCode::new_step(&mut prog);
-    let mut ctx = Ctx::new(cpu, (50, 50));
-    ctx.dfs();
+    let cpu = IntCode::new((), prog);
+    let mut ctx = Ctx::new((50, 50));
+    ctx.dfs(&cpu, Point::default());
     ctx.print();
 
     let p = ctx.board.find(|it| it == &Cell::Target).unwrap();
@@ -41,55 +41,34 @@ fn bfs(board: &Board<Cell>, start: Point) -> Board<Option<usize>> {
     dists
 }
 
-struct Ctx<'a> {
-    cpu: IntCode<'a, StepIo>,
+struct Ctx {
     board: Board<Cell>,
-    pos: Point,
 }
 
-impl Ctx<'_> {
-    fn new(cpu: IntCode<StepIo>, dim: (usize, usize)) -> Ctx {
+impl Ctx {
+    fn new(dim: (usize, usize)) -> Ctx {
         let mut board = Board::new(dim, Cell::Fog).move_origin_to_center();
-        let pos = Point::default();
-        board[pos] = Cell::Empty;
-        Ctx { cpu, board, pos }
+        board[Point::default()] = Cell::Empty;
+        Ctx { board }
     }
 
-    fn dfs(&mut self) {
+    /// Explores the maze from `pos`, where the droid controlled by `cpu` is.
+    fn dfs(&mut self, cpu: &IntCode<()>, pos: Point) {
         for &dir in Direction::ALL.iter() {
-            let p = self.pos + dir.delta();
+            let p = pos + dir.delta();
             if self.board[p] == Cell::Fog {
-                let cell = self.step(dir);
+                let mut cpu = cpu.clone();
+                let cell = step(&mut cpu, dir);
                 self.board[p] = cell;
                 match cell {
                     Cell::Fog => unreachable!(),
-                    Cell::Empty | Cell::Target => {
-                        self.pos += dir.delta();
-                        self.dfs();
-                        self.step(dir.rev());
-                        self.pos += dir.rev().delta();
-                    }
+                    Cell::Empty | Cell::Target => self.dfs(&cpu, p),
                     Cell::Wall => (),
                 }
             }
         }
     }
 
-    fn step(&mut self, d: Direction) -> Cell {
-        self.cpu.input(match d {
-            Direction::Up => 1,
-            Direction::Right => 4,
-            Direction::Down => 2,
-            Direction::Left => 3,
-        });
-        match self.cpu.output() {
-            0 => Cell::Wall,
-            1 => Cell::Empty,
-            2 => Cell::Target,
-            _ => panic!(),
-        }
-    }
-
     fn print(&self) {
         self.board.print(|cell| match cell {
             Cell::Empty => '.',
@@ -100,6 +79,23 @@ impl Ctx<'_> {
     }
 }
 
+fn step(cpu: &mut IntCode<()>, d: Direction) -> Cell {
+    assert_eq!(cpu.run_until_event().unwrap(), Event::NeedsInput);
+    cpu.provide_input(match d {
+        Direction::Up => 1,
+        Direction::Right => 4,
+        Direction::Down => 2,
+        Direction::Left => 3,
+    })
+    .unwrap();
+    match cpu.run_until_event().unwrap() {
+        Event::Output(0) => Cell::Wall,
+        Event::Output(1) => Cell::Empty,
+        Event::Output(2) => Cell::Target,
+        _ => panic!(),
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Cell {
     Fog,
@@ -1,11 +1,13 @@
 use std::fs;
 
-use aoc::{parse_memory, IntCode, MemIo, Point, Result};
+use aoc::{parse_memory, IntCode, MemIo, PagedMemory, Point, Result};
 
 fn main() -> Result<()> {
     let prog = fs::read_to_string("./input/day19.in")?;
     let prog = parse_memory(&prog)?;
-    let ctx = Ctx { prog };
+    let mut proto = IntCode::new(MemIo::new(vec![]), PagedMemory::new(&prog));
+    proto.predecode(0..prog.len() as i64);
+    let ctx = Ctx { proto };
 
     let mut lo = 10;
     let mut hi = 10000;
@@ -24,13 +26,14 @@ fn main() -> Result<()> {
 }
 
 struct Ctx {
-    prog: Vec<i64>,
+    /// Every probe runs a fresh clone of this.
+    proto: IntCode<MemIo, PagedMemory>,
 }
 
 impl Ctx {
     fn is_covered(&self, point: Point) -> bool {
-        let mut prog = self.prog.clone();
-        let mut cpu = IntCode::new(MemIo::new(vec![point.0, point.1]), &mut prog);
+        let mut cpu = self.proto.clone();
+        cpu.io = MemIo::new(vec![point.0, point.1]);
         cpu.run().unwrap();
         let output = cpu.io.into_output();
         assert!(output.len() == 1);
@@ -1,9 +1,26 @@
+//! Runs the springdroid, reading the springscript from stdin or from a
+//! script file.
+//!
+//! ```text
+//! day21 [--echo] [script.txt]
+//! ```
 use aoc::{parse_memory, AsciiIo, IntCode, Result};
 
 fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (echo, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "--echo" => (true, rest),
+        _ => (false, &args[..]),
+    };
+    let io = match args {
+        [] => AsciiIo::new(),
+        [path] => AsciiIo::script(&std::fs::read_to_string(path)?).with_echo(echo),
+        _ => Err("usage: day21 [--echo] [script.txt]")?,
+    };
+
     let prog = std::fs::read_to_string("./input/day21.in")?;
     let mut prog = parse_memory(&prog)?;
-    let mut cpu = IntCode::new(AsciiIo::new(), &mut prog);
+    let mut cpu = IntCode::new(io, &mut prog);
     cpu.run()?;
     Ok(())
 }
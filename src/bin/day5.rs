@@ -16,15 +16,14 @@ fn main() -> Result<()> {
 
 #[test]
 fn test_examples() {
-    use aoc::MemIo;
+    use aoc::adapt::IterIo;
 
     fn check(memory: Vec<i64>, tests: Vec<(i64, i64)>) {
         for (i, o) in tests {
             let mut mem = memory.clone();
-            let mut mem_io = MemIo::new(vec![i]);
-            let mut computer = IntCode::new(mem_io, &mut mem);
-            computer.run().unwrap();
-            let output = computer.io.into_output();
+            let mut output = Vec::new();
+            let io = IterIo::new(Some(i), |it| output.push(it));
+            IntCode::new(io, &mut mem).run().unwrap();
             assert_eq!(
                 output,
                 vec![o],
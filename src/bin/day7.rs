@@ -1,6 +1,6 @@
 use std::io::Read;
 
-use aoc::{parse_memory, IntCode, Result, SlotIo};
+use aoc::{parse_memory, topology::Topology, Result};
 
 fn main() -> Result<()> {
     let mut buf = String::new();
@@ -19,41 +19,20 @@ fn maximize_thrust(program: &[i64]) -> i64 {
 }
 
 fn run(program: &[i64], phases: &[i64]) -> i64 {
-    let slot = &SlotIo::default();
-    let mut amps = (0..phases.len())
-        .map(|_| (program.to_vec(), slot))
-        .collect::<Vec<_>>();
-    let mut amps = phases
+    let mut topo = Topology::new();
+    let amps = phases
         .iter()
-        .zip(&mut amps)
-        .map(|(phase, (mem, io))| {
-            let mut ic = IntCode::new(*io, mem.as_mut_slice());
-            slot.set(*phase);
-            while !slot.clear_read() {
-                assert!(ic.step().unwrap());
-            }
-            ic
+        .enumerate()
+        .map(|(i, &phase)| {
+            let inputs = if i == 0 { vec![phase, 0] } else { vec![phase] };
+            topo.add(program.to_vec(), &inputs)
         })
         .collect::<Vec<_>>();
-
-    slot.set(0);
-    let mut thrust = 0;
-    'outer: loop {
-        for amp in amps.iter_mut() {
-            while !slot.clear_read() {
-                if !amp.step().unwrap() {
-                    break 'outer;
-                }
-            }
-            while !slot.clear_write() {
-                if !amp.step().unwrap() {
-                    break 'outer;
-                }
-            }
-        }
-        thrust = slot.get();
+    for (i, &amp) in amps.iter().enumerate() {
+        topo.connect(amp, amps[(i + 1) % amps.len()]);
     }
-    thrust
+    let thrust = topo.sink(amps[amps.len() - 1]);
+    topo.run().unwrap().last(thrust).unwrap()
 }
 
 fn permutations<T, F>(xs: &mut [T], mut f: F)
@@ -1,13 +1,12 @@
 use std::io::{stdin, Read};
 
-use aoc::{extend_memory, parse_memory, IntCode, MemIo, Result};
+use aoc::{parse_memory, IntCode, MemIo, Result};
 
 fn main() -> Result<()> {
     let mut buf = String::new();
     stdin().read_to_string(&mut buf)?;
 
     let mut mem = parse_memory(&buf)?;
-    extend_memory(&mut mem);
 
     let io = MemIo::new(vec![2]);
     let mut computer = IntCode::new(io, &mut mem);
@@ -20,11 +19,9 @@ fn main() -> Result<()> {
 #[test]
 fn test_examples() {
     fn run(mut prog: Vec<i64>) -> Vec<i64> {
-        extend_memory(&mut prog);
-        let mut io = aio::MemIo::new(vec![]);
-        let computer = IntCode::new(&mut io, &mut prog);
+        let mut computer = IntCode::new(MemIo::new(vec![]), &mut prog);
         computer.run().unwrap();
-        io.into_output()
+        computer.io.into_output()
     }
 
     assert_eq!(
@@ -0,0 +1,175 @@
+//! Interactive Intcode debugger.
+//!
+//! Usage: `debug [--ascii] program.in`. Commands are read from stdin, so they
+//! interleave with the program's own input. Executed instructions are
+//! recorded, so the session can also run backwards.
+use std::{
+    convert::TryFrom,
+    fs,
+    io::{self, BufRead, Write},
+};
+
+use aoc::{
+    debugger::{Debugger, Stop},
+    parse_memory, AsciiIo, IntCode, Io, Result, StdIo,
+};
+
+const HELP: &str = "\
+s, step            execute one instruction
+n, next            step over the current instruction
+c, continue        run until a breakpoint, a watchpoint or halt
+rs, reverse-step   undo the last instruction
+rc, reverse-continue [ADDR]
+                   run backwards to a breakpoint or a watchpoint, or to
+                   the last write of ADDR
+b, break ADDR      set a breakpoint
+d, delete ADDR     delete a breakpoint
+w, watch ADDR      stop when the cell at ADDR changes
+u, unwatch ADDR    delete a watchpoint
+i, info            show registers, breakpoints and watchpoints
+x ADDR [N]         examine N memory cells
+set ip|rb|ADDR V   modify a register or a memory cell, clearing the history
+l, list [N]        disassemble around ip
+q, quit            exit the debugger";
+
+fn main() -> Result<()> {
+    let mut ascii = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--ascii" => ascii = true,
+            _ => path = Some(arg),
+        }
+    }
+    let path = path.ok_or("usage: debug [--ascii] program.in")?;
+    let mem = parse_memory(&fs::read_to_string(path)?)?;
+    if ascii {
+        session(Debugger::new(IntCode::new(AsciiIo::new(), mem)))
+    } else {
+        session(Debugger::new(IntCode::new(StdIo::new(), mem)))
+    }
+}
+
+fn session<IO: Io>(mut dbg: Debugger<IO>) -> Result<()> {
+    let stdin = io::stdin();
+    let mut prev = String::new();
+    loop {
+        eprint!("(ic {}) ", dbg.cpu.ip());
+        io::stderr().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let line = match line.trim() {
+            "" => prev.clone(),
+            it => it.to_string(),
+        };
+        match command(&mut dbg, &line) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(e) => eprintln!("error: {}", e),
+        }
+        prev = line;
+    }
+}
+
+fn command<IO: Io>(dbg: &mut Debugger<IO>, line: &str) -> Result<bool> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let arg = |idx: usize| -> Result<i64> {
+        let word = words.get(idx).ok_or("missing argument")?;
+        Ok(word.parse()?)
+    };
+    let count = |idx: usize, default: usize| -> Result<usize> {
+        match words.get(idx) {
+            Some(word) => Ok(word.parse()?),
+            None => Ok(default),
+        }
+    };
+    match words.as_slice() {
+        [] => (),
+        ["s"] | ["step"] => {
+            let stop = dbg.step()?;
+            report(dbg, stop)
+        }
+        ["n"] | ["next"] => {
+            let stop = dbg.step_over()?;
+            report(dbg, stop)
+        }
+        ["c"] | ["continue"] => {
+            let stop = dbg.cont()?;
+            report(dbg, stop)
+        }
+        ["rs"] | ["reverse-step"] => {
+            let stop = dbg.reverse_step()?;
+            report(dbg, stop)
+        }
+        ["rc"] | ["reverse-continue"] => {
+            let stop = dbg.reverse_cont()?;
+            report(dbg, stop)
+        }
+        ["rc", _] | ["reverse-continue", _] => {
+            let stop = dbg.reverse_to_write(arg(1)?)?;
+            report(dbg, stop)
+        }
+        ["b", ..] | ["break", ..] => {
+            dbg.add_breakpoint(arg(1)?);
+        }
+        ["d", ..] | ["delete", ..] => {
+            if !dbg.remove_breakpoint(arg(1)?) {
+                Err("no such breakpoint")?
+            }
+        }
+        ["w", ..] | ["watch", ..] => {
+            dbg.add_watchpoint(arg(1)?)?;
+        }
+        ["u", ..] | ["unwatch", ..] => {
+            if !dbg.remove_watchpoint(arg(1)?) {
+                Err("no such watchpoint")?
+            }
+        }
+        ["i"] | ["info"] => {
+            eprintln!("ip = {}, rb = {}", dbg.cpu.ip(), dbg.cpu.bp());
+            eprintln!("history: {} instructions", dbg.history_len());
+            eprintln!("breakpoints: {:?}", dbg.breakpoints().collect::<Vec<_>>());
+            eprintln!("watchpoints: {:?}", dbg.watchpoints().collect::<Vec<_>>());
+        }
+        ["x", ..] => {
+            let addr = arg(1)?;
+            let n = i64::try_from(count(2, 1)?)?;
+            let end = addr.checked_add(n).ok_or("address out of range")?;
+            for addr in addr..end {
+                eprintln!("{:>5}: {}", addr, dbg.peek(addr)?);
+            }
+        }
+        ["set", "ip", _] => dbg.set_ip(arg(2)?),
+        ["set", "rb", _] => dbg.set_bp(arg(2)?),
+        ["set", _, _] => dbg.poke(arg(1)?, arg(2)?)?,
+        ["l", ..] | ["list", ..] => {
+            for item in dbg.context(count(1, 8)?) {
+                let marker = if item.addr() == dbg.cpu.ip() {
+                    "=>"
+                } else {
+                    "  "
+                };
+                eprintln!("{}{}", marker, item);
+            }
+        }
+        ["q"] | ["quit"] => return Ok(false),
+        _ => eprintln!("{}", HELP),
+    }
+    Ok(true)
+}
+
+fn report<IO: Io>(dbg: &Debugger<IO>, stop: Stop) {
+    match stop {
+        Stop::Step => (),
+        Stop::Breakpoint(addr) => eprintln!("breakpoint at {}", addr),
+        Stop::Watchpoint { addr, old, new } => eprintln!("[{}]: {} -> {}", addr, old, new),
+        Stop::Halted => eprintln!("halted"),
+        Stop::AwaitingInput => eprintln!("end of input"),
+        Stop::HistoryStart => eprintln!("start of history"),
+    }
+    if let Some(item) = dbg.context(1).last() {
+        eprintln!("=>{}", item);
+    }
+}
@@ -0,0 +1,32 @@
+//! Usage: `disasm [--dot|--decompile] [program.in]`. With `--dot`, prints the
+//! control-flow graph in Graphviz format instead of a listing, with
+//! `--decompile` structured pseudocode.
+use std::fs;
+
+use aoc::{cfg::Cfg, decompile::decompile, disasm, parse_memory, read_stdin_to_string, Result};
+
+fn main() -> Result<()> {
+    let mut dot = false;
+    let mut pseudo = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--dot" => dot = true,
+            "--decompile" => pseudo = true,
+            _ => path = Some(arg),
+        }
+    }
+    let text = match path {
+        Some(path) => fs::read_to_string(path)?,
+        None => read_stdin_to_string()?,
+    };
+    let mem = parse_memory(&text)?;
+    if dot {
+        print!("{}", Cfg::new(&mem).to_dot());
+    } else if pseudo {
+        print!("{}", decompile(&mem));
+    } else {
+        print!("{}", disasm::listing(&mem));
+    }
+    Ok(())
+}
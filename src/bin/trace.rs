@@ -0,0 +1,198 @@
+//! Records and inspects Intcode execution traces, and Io tapes.
+//!
+//! ```text
+//! trace record [--ascii] program.in out.trace
+//! trace profile [--ascii] program.in
+//! trace tape [--ascii] program.in out.tape
+//! trace replay program.in in.tape
+//! trace show out.trace [--ip A[..B]] [--steps A..B] [--op MNEMONIC] [--write ADDR] [--io]
+//! ```
+use std::{fs, io::BufReader, ops::Range};
+
+use aoc::{
+    parse_memory,
+    profile::Profiler,
+    tape::{Recorder, Replay},
+    trace::{read_trace, TraceRecord, TraceWriter},
+    AsciiIo, IntCode, Io, Result, StdIo,
+};
+
+fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(|it| it.as_str()) {
+        Some("record") => record(&args[1..]),
+        Some("show") => show(&args[1..]),
+        Some("profile") => profile(&args[1..]),
+        Some("tape") => tape(&args[1..]),
+        Some("replay") => replay(&args[1..]),
+        _ => Err("usage: trace record|show|profile|tape|replay ...")?,
+    }
+}
+
+fn record(args: &[String]) -> Result<()> {
+    let (ascii, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "--ascii" => (true, rest),
+        _ => (false, args),
+    };
+    let (prog, out) = match args {
+        [prog, out] => (prog, out),
+        _ => Err("usage: trace record [--ascii] program.in out.trace")?,
+    };
+    let mem = parse_memory(&fs::read_to_string(prog)?)?;
+    let trace = TraceWriter::create(out)?;
+    if ascii {
+        run(IntCode::new(AsciiIo::new(), mem), trace)
+    } else {
+        run(IntCode::new(StdIo::new(), mem), trace)
+    }
+}
+
+fn run<IO: Io, W: std::io::Write>(mut cpu: IntCode<IO>, mut trace: TraceWriter<W>) -> Result<()> {
+    let res = cpu.run_traced(&mut trace);
+    // Keep the trace leading up to a fault, it's the interesting part.
+    trace.finish()?;
+    res?;
+    Ok(())
+}
+
+fn profile(args: &[String]) -> Result<()> {
+    let (ascii, prog) = match args {
+        [flag, prog] if flag == "--ascii" => (true, prog),
+        [prog] => (false, prog),
+        _ => Err("usage: trace profile [--ascii] program.in")?,
+    };
+    let mem = parse_memory(&fs::read_to_string(prog)?)?;
+    let mut profiler = Profiler::new();
+    let res = if ascii {
+        IntCode::new(AsciiIo::new(), mem).run_traced(&mut profiler)
+    } else {
+        IntCode::new(StdIo::new(), mem).run_traced(&mut profiler)
+    };
+    // The program's output goes to stdout.
+    eprint!("{}", profiler.report());
+    res?;
+    Ok(())
+}
+
+fn tape(args: &[String]) -> Result<()> {
+    let (ascii, prog, out) = match args {
+        [flag, prog, out] if flag == "--ascii" => (true, prog, out),
+        [prog, out] => (false, prog, out),
+        _ => Err("usage: trace tape [--ascii] program.in out.tape")?,
+    };
+    let mem = parse_memory(&fs::read_to_string(prog)?)?;
+    if ascii {
+        let mut cpu = IntCode::new(Recorder::create(AsciiIo::new(), out)?, mem);
+        let res = cpu.run();
+        cpu.io.finish()?;
+        res?;
+    } else {
+        let mut cpu = IntCode::new(Recorder::create(StdIo::new(), out)?, mem);
+        let res = cpu.run();
+        cpu.io.finish()?;
+        res?;
+    }
+    Ok(())
+}
+
+fn replay(args: &[String]) -> Result<()> {
+    let (prog, tape) = match args {
+        [prog, tape] => (prog, tape),
+        _ => Err("usage: trace replay program.in in.tape")?,
+    };
+    let mem = parse_memory(&fs::read_to_string(prog)?)?;
+    let mut cpu = IntCode::new(Replay::open(tape)?, mem);
+    cpu.run()?;
+    cpu.io.finish(cpu.ip())?;
+    println!("replayed {} events", cpu.io.step());
+    Ok(())
+}
+
+#[derive(Default)]
+struct Filter {
+    ip: Option<Range<i64>>,
+    steps: Option<Range<i64>>,
+    op: Option<String>,
+    write: Option<i64>,
+    io: bool,
+}
+
+impl Filter {
+    fn matches(&self, record: &TraceRecord) -> bool {
+        self.ip.as_ref().is_none_or(|it| it.contains(&record.ip))
+            && self
+                .steps
+                .as_ref()
+                .is_none_or(|it| it.contains(&(record.step as i64)))
+            && self.op.as_ref().is_none_or(|it| it == record.op.mnemonic())
+            && self
+                .write
+                .is_none_or(|addr| record.writes.iter().any(|&(a, _)| a == addr))
+            && (!self.io || record.input.is_some() || record.output.is_some())
+    }
+}
+
+/// Parses `A..B` or a single `A`.
+fn range(text: &str) -> Result<Range<i64>> {
+    let mut parts = text.splitn(2, "..");
+    let lo = parts.next().unwrap().parse()?;
+    let hi = match parts.next() {
+        Some(hi) => hi.parse()?,
+        None => lo + 1,
+    };
+    Ok(lo..hi)
+}
+
+fn show(args: &[String]) -> Result<()> {
+    let (path, mut args) = match args.split_first() {
+        Some((path, rest)) => (path, rest.iter()),
+        None => Err("usage: trace show out.trace [filters]")?,
+    };
+    let mut filter = Filter::default();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", flag));
+        match flag.as_str() {
+            "--ip" => filter.ip = Some(range(value()?)?),
+            "--steps" => filter.steps = Some(range(value()?)?),
+            "--op" => filter.op = Some(value()?.clone()),
+            "--write" => filter.write = Some(value()?.parse()?),
+            "--io" => filter.io = true,
+            _ => Err(format!("unknown flag: {}", flag))?,
+        }
+    }
+
+    let file = BufReader::new(fs::File::open(path)?);
+    for record in read_trace(file) {
+        let record = record?;
+        if filter.matches(&record) {
+            println!("{}", pretty(&record));
+        }
+    }
+    Ok(())
+}
+
+fn pretty(record: &TraceRecord) -> String {
+    let args = record
+        .args
+        .iter()
+        .map(|it| it.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut res = format!(
+        "{:>8} {:>5}: {:<32} ({})",
+        record.step,
+        record.ip,
+        record.op.to_string(),
+        args
+    );
+    for (addr, value) in record.writes.iter() {
+        res += &format!("  [{}] <- {}", addr, value);
+    }
+    if let Some(value) = record.input {
+        res += &format!("  in {}", value);
+    }
+    if let Some(value) = record.output {
+        res += &format!("  out {}", value);
+    }
+    res
+}
@@ -0,0 +1,15 @@
+//! Translates an Intcode program to Rust, see `aoc::transpile`.
+//!
+//! Usage: `transpile program.in [name] > program.rs`.
+use std::fs;
+
+use aoc::{parse_memory, transpile::transpile, Result};
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().ok_or("usage: transpile program.in [name]")?;
+    let name = args.next().unwrap_or_else(|| "run".to_string());
+    let mem = parse_memory(&fs::read_to_string(path)?)?;
+    print!("{}", transpile(&mem, &name));
+    Ok(())
+}
@@ -0,0 +1,462 @@
+//! Control-flow graph reconstruction for Intcode images.
+//!
+//! Code is discovered by following control flow from address 0, so data is
+//! never mistaken for instructions. Jumps through memory can't be followed
+//! statically, with one exception: the calling convention of compiled
+//! Intcode programs, where a call stores the return address in the frame and
+//! jumps to the function,
+//!
+//! ```text
+//!         add #ret, #0 -> [rb+1]
+//!         jt #1, #func
+//! ret:    ...
+//! ```
+//!
+//! and the function returns by popping its frame and jumping through it:
+//!
+//! ```text
+//!         arb #-3
+//!         jt #1, [rb+1]
+//! ```
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt::Write,
+};
+
+use crate::{ArithOp, JumpOp, Op, Value};
+
+/// How control leaves a basic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// Runs into the next block.
+    Fall(i64),
+    Halt,
+    /// A jump to an immediate target. `fall` is `None` for unconditional
+    /// jumps.
+    Jump {
+        taken: i64,
+        fall: Option<i64>,
+    },
+    Call {
+        target: i64,
+        ret: i64,
+    },
+    /// A jump through `[rb+N]` after adjusting the relative base.
+    Return {
+        fall: Option<i64>,
+    },
+    /// Any other jump through memory.
+    Indirect {
+        fall: Option<i64>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: i64,
+    pub end: i64,
+    pub ops: Vec<(i64, Op)>,
+    pub exit: Exit,
+}
+
+impl Block {
+    /// Successors within the same function. Calls are assumed to return.
+    pub fn succs(&self) -> Vec<i64> {
+        match self.exit {
+            Exit::Fall(next) => vec![next],
+            Exit::Halt => vec![],
+            Exit::Jump { taken, fall } => Some(taken).into_iter().chain(fall).collect(),
+            Exit::Call { ret, .. } => vec![ret],
+            Exit::Return { fall } | Exit::Indirect { fall } => fall.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub entry: i64,
+    /// Size of the frame allocated by an `arb #N` in the entry block.
+    pub frame: Option<i64>,
+    pub blocks: BTreeSet<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    pub blocks: BTreeMap<i64, Block>,
+    /// The program's entry point at address 0 comes first.
+    pub functions: Vec<Function>,
+}
+
+impl Cfg {
+    pub fn new(image: &[i64]) -> Cfg {
+        let instrs = instructions(image);
+        let blocks = split_blocks(&instrs);
+
+        let mut entries = BTreeSet::new();
+        for block in blocks.values() {
+            if let Exit::Call { target, .. } = block.exit {
+                entries.insert(target);
+            }
+        }
+        entries.remove(&0);
+        let functions = std::iter::once(0)
+            .chain(entries)
+            .filter(|it| blocks.contains_key(it))
+            .map(|entry| function(&blocks, entry))
+            .collect();
+        Cfg { blocks, functions }
+    }
+
+    /// Blocks ending in a jump through memory which isn't a return.
+    pub fn indirect_jumps(&self) -> impl Iterator<Item = &Block> + '_ {
+        self.blocks
+            .values()
+            .filter(|it| matches!(it.exit, Exit::Indirect { .. }))
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut res = String::new();
+        let w = &mut res;
+        writeln!(w, "digraph cfg {{").unwrap();
+        writeln!(w, "    node [shape=box, fontname=monospace];").unwrap();
+
+        let mut placed = HashSet::new();
+        for f in self.functions.iter() {
+            writeln!(w, "    subgraph cluster_{} {{", f.entry).unwrap();
+            writeln!(w, "        label=\"fn_{}\";", f.entry).unwrap();
+            for &start in f.blocks.iter() {
+                if placed.insert(start) {
+                    writeln!(w, "        b{};", start).unwrap();
+                }
+            }
+            writeln!(w, "    }}").unwrap();
+        }
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for (ip, op) in block.ops.iter() {
+                label += &format!("{}: {}\\l", ip, op);
+            }
+            let color = match block.exit {
+                Exit::Indirect { .. } => ", color=red",
+                _ => "",
+            };
+            writeln!(w, "    b{} [label=\"{}\"{}];", block.start, label, color).unwrap();
+
+            let edge = |w: &mut String, to: i64, attrs: &str| {
+                writeln!(w, "    b{} -> b{}{};", block.start, to, attrs).unwrap();
+            };
+            match block.exit {
+                Exit::Fall(next) => edge(w, next, ""),
+                Exit::Halt => (),
+                Exit::Jump { taken, fall } => match fall {
+                    None => edge(w, taken, ""),
+                    Some(fall) => {
+                        edge(w, taken, " [label=T]");
+                        edge(w, fall, " [label=F]");
+                    }
+                },
+                Exit::Call { target, ret } => {
+                    edge(w, target, " [style=dashed, label=call]");
+                    edge(w, ret, "");
+                }
+                Exit::Return { fall } | Exit::Indirect { fall } => {
+                    if let Some(fall) = fall {
+                        edge(w, fall, " [label=F]");
+                    }
+                }
+            }
+        }
+        writeln!(w, "}}").unwrap();
+        res
+    }
+}
+
+/// Decodes the instructions reachable from address 0 by falling through,
+/// jumping to an immediate target or returning from a call.
+pub fn instructions(image: &[i64]) -> BTreeMap<i64, (i64, Op)> {
+    let load = |addr: i64| match addr {
+        _ if addr < 0 => None,
+        _ => Some(image.get(addr as usize).copied().unwrap_or(0)),
+    };
+    let mut res = BTreeMap::new();
+    // Stored constants and addresses following unconditional jumps. Where
+    // these meet, there's a return address.
+    let mut consts = HashSet::new();
+    let mut after_jumps = HashSet::new();
+
+    let mut work = vec![0];
+    loop {
+        while let Some(ip) = work.pop() {
+            if ip < 0 || ip as usize >= image.len() || res.contains_key(&ip) {
+                continue;
+            }
+            let (size, op) = match Op::decode(ip, 0, load) {
+                Ok(it) => it,
+                Err(_) => continue,
+            };
+            res.insert(ip, (size, op));
+            match op {
+                Op::Halt => (),
+                Op::Jump { op, cond, tgt } => {
+                    let taken = taken(op, cond);
+                    if taken != Some(false) {
+                        if let Value::Immediate(tgt) = tgt {
+                            work.push(tgt);
+                        }
+                    }
+                    if taken == Some(true) {
+                        after_jumps.insert(ip + size);
+                    } else {
+                        work.push(ip + size);
+                    }
+                }
+                _ => {
+                    if let Some(value) = constant(&op) {
+                        consts.insert(value);
+                    }
+                    work.push(ip + size);
+                }
+            }
+        }
+        work.extend(
+            consts
+                .intersection(&after_jumps)
+                .filter(|it| !res.contains_key(it)),
+        );
+        if work.is_empty() {
+            return res;
+        }
+    }
+}
+
+/// Whether a jump with this condition is always or never taken.
+fn taken(op: JumpOp, cond: Value) -> Option<bool> {
+    match cond {
+        Value::Immediate(it) => Some((it != 0) == (op == JumpOp::IfTrue)),
+        Value::Addr(_) => None,
+    }
+}
+
+/// The value written by `op` if it's a constant, like in `add #5, #0 -> [x]`.
+/// Overflowing arithmetic isn't, its result depends on the overflow policy.
+pub(crate) fn constant(op: &Op) -> Option<i64> {
+    let (op, lhs, rhs) = match *op {
+        Op::Arith {
+            op,
+            lhs: Value::Immediate(lhs),
+            rhs: Value::Immediate(rhs),
+            ..
+        } => (op, lhs, rhs),
+        _ => return None,
+    };
+    match op {
+        ArithOp::Add => lhs.checked_add(rhs),
+        ArithOp::Mul => lhs.checked_mul(rhs),
+        ArithOp::LessThan => Some((lhs < rhs) as i64),
+        ArithOp::Equals => Some((lhs == rhs) as i64),
+    }
+}
+
+fn split_blocks(instrs: &BTreeMap<i64, (i64, Op)>) -> BTreeMap<i64, Block> {
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+    for (&ip, &(size, op)) in instrs.iter() {
+        if let Op::Jump { op, cond, tgt } = op {
+            if taken(op, cond) != Some(false) {
+                if let Value::Immediate(tgt) = tgt {
+                    leaders.insert(tgt);
+                }
+            }
+            leaders.insert(ip + size);
+        }
+        if let Op::Halt = op {
+            leaders.insert(ip + size);
+        }
+    }
+
+    let mut res = BTreeMap::new();
+    let mut ops: Vec<(i64, Op)> = Vec::new();
+    let mut next = 0;
+    for (&ip, &(size, op)) in instrs.iter() {
+        if !ops.is_empty() && (ip != next || leaders.contains(&ip)) {
+            let block = finish_block(std::mem::take(&mut ops), next, Exit::Fall(next));
+            res.insert(block.start, block);
+        }
+        ops.push((ip, op));
+        next = ip + size;
+        let exit = match op {
+            Op::Halt => Exit::Halt,
+            Op::Jump { op, cond, tgt } => jump_exit(&ops, op, cond, tgt, next),
+            _ => continue,
+        };
+        let block = finish_block(std::mem::take(&mut ops), next, exit);
+        res.insert(block.start, block);
+    }
+    if !ops.is_empty() {
+        let block = finish_block(ops, next, Exit::Fall(next));
+        res.insert(block.start, block);
+    }
+    res
+}
+
+fn finish_block(ops: Vec<(i64, Op)>, end: i64, exit: Exit) -> Block {
+    Block {
+        start: ops[0].0,
+        end,
+        ops,
+        exit,
+    }
+}
+
+fn jump_exit(ops: &[(i64, Op)], op: JumpOp, cond: Value, tgt: Value, next: i64) -> Exit {
+    let fall = match taken(op, cond) {
+        Some(false) => return Exit::Fall(next),
+        Some(true) => None,
+        None => Some(next),
+    };
+    match tgt {
+        Value::Immediate(target) => {
+            let stores_ret = ops.iter().any(|(_, op)| constant(op) == Some(next));
+            if fall.is_none() && stores_ret {
+                Exit::Call { target, ret: next }
+            } else {
+                Exit::Jump {
+                    taken: target,
+                    fall,
+                }
+            }
+        }
+        Value::Addr(addr) => {
+            let adjusts_bp = ops.iter().any(|(_, op)| matches!(op, Op::AdjBp { .. }));
+            if addr.rel && adjusts_bp {
+                Exit::Return { fall }
+            } else {
+                Exit::Indirect { fall }
+            }
+        }
+    }
+}
+
+fn function(blocks: &BTreeMap<i64, Block>, entry: i64) -> Function {
+    let frame = blocks[&entry].ops.iter().find_map(|(_, op)| match op {
+        Op::AdjBp {
+            adj: Value::Immediate(it),
+        } => Some(*it),
+        _ => None,
+    });
+    let mut res = Function {
+        entry,
+        frame,
+        blocks: BTreeSet::new(),
+    };
+    let mut work = vec![entry];
+    while let Some(start) = work.pop() {
+        if !blocks.contains_key(&start) || !res.blocks.insert(start) {
+            continue;
+        }
+        work.extend(blocks[&start].succs());
+    }
+    res
+}
+
+#[test]
+fn test_cfg() {
+    use crate::asm::assemble;
+
+    let prog = assemble(
+        "
+                in -> [rb+2]
+                add #ret, #0 -> [rb+1]
+                jt #1, #double
+        ret:    out [rb+2]
+                hlt
+        double: arb #3
+                add [rb-1], [rb-1] -> [rb-1]
+                jf [rb-1], #zero
+                arb #-3
+                jt #1, [rb+1]
+        zero:   out #0
+                arb #-3
+                jt #1, [rb+1]
+        ",
+    )
+    .unwrap();
+    let cfg = Cfg::new(&prog);
+
+    let exits = cfg
+        .blocks
+        .values()
+        .map(|it| (it.start, it.exit))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        exits,
+        vec![
+            (0, Exit::Call { target: 12, ret: 9 }),
+            (9, Exit::Halt),
+            (
+                12,
+                Exit::Jump {
+                    taken: 26,
+                    fall: Some(21)
+                }
+            ),
+            (21, Exit::Return { fall: None }),
+            (26, Exit::Return { fall: None }),
+        ]
+    );
+    assert_eq!(cfg.blocks[&12].ops.len(), 3);
+    assert_eq!(cfg.indirect_jumps().count(), 0);
+
+    let entries = cfg
+        .functions
+        .iter()
+        .map(|it| {
+            (
+                it.entry,
+                it.frame,
+                it.blocks.iter().copied().collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        entries,
+        vec![(0, None, vec![0, 9]), (12, Some(3), vec![12, 21, 26])]
+    );
+
+    let dot = cfg.to_dot();
+    assert!(
+        dot.contains("    b0 -> b12 [style=dashed, label=call];\n"),
+        "{}",
+        dot
+    );
+    assert!(dot.contains("    b12 -> b26 [label=T];\n"), "{}", dot);
+    assert!(
+        dot.contains("label=\"9: out [rb+2]\\l11: hlt\\l\""),
+        "{}",
+        dot
+    );
+
+    // Jumps through absolute addresses can't be followed.
+    let prog = assemble(
+        "
+                in -> [t]
+                jt [t], [t]
+                hlt
+        t:      .data 0
+        ",
+    )
+    .unwrap();
+    let cfg = Cfg::new(&prog);
+    let indirect = cfg.indirect_jumps().map(|it| it.start).collect::<Vec<_>>();
+    assert_eq!(indirect, vec![0]);
+    assert_eq!(cfg.blocks[&0].exit, Exit::Indirect { fall: Some(5) });
+
+    // Constant folding doesn't overflow.
+    let prog = [1101, i64::MAX, 1, 0, 99];
+    let cfg = Cfg::new(&prog);
+    assert_eq!(cfg.blocks[&0].exit, Exit::Halt);
+    assert!(cfg.to_dot().contains("b0"));
+    crate::decompile::decompile(&prog);
+    crate::transpile::transpile(&prog, "prog");
+}
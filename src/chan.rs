@@ -0,0 +1,163 @@
+//! Channel backed [`Io`], for running machines on their own threads.
+//!
+//! Every machine reads from a [`Receiver`] and writes to a [`Sender`], or to a
+//! [`SyncSender`] to bound the number of values in flight. When a machine
+//! stops, [`spawn`] closes its output, so peers blocked on reading from it
+//! see the end of their input and stop in [`Status::AwaitingInput`] instead
+//! of hanging.
+use std::{
+    error, fmt,
+    sync::mpsc::{Receiver, RecvTimeoutError, Sender, SyncSender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{IntCode, IntcodeError, Io, Memory, Result, Status};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChanError {
+    /// Writing to a channel whose other end is gone.
+    Disconnected,
+    /// No value arrived within the read timeout.
+    Timeout,
+}
+
+impl error::Error for ChanError {}
+impl fmt::Display for ChanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChanError::Disconnected => write!(f, "channel disconnected"),
+            ChanError::Timeout => write!(f, "timed out waiting for input"),
+        }
+    }
+}
+
+enum Output {
+    Unbounded(Sender<i64>),
+    Bounded(SyncSender<i64>),
+}
+
+pub struct ChanIo {
+    input: Receiver<i64>,
+    output: Option<Output>,
+    timeout: Option<Duration>,
+}
+
+impl ChanIo {
+    pub fn new(input: Receiver<i64>, output: Sender<i64>) -> ChanIo {
+        ChanIo {
+            input,
+            output: Some(Output::Unbounded(output)),
+            timeout: None,
+        }
+    }
+    /// Like `new`, but writes block while the channel is full.
+    pub fn bounded(input: Receiver<i64>, output: SyncSender<i64>) -> ChanIo {
+        ChanIo {
+            input,
+            output: Some(Output::Bounded(output)),
+            timeout: None,
+        }
+    }
+    /// Fails reads which block for longer than `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> ChanIo {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Drops the output, so that readers see the end of the stream. Further
+    /// writes fail.
+    pub fn close(&mut self) {
+        self.output = None;
+    }
+    /// Returns the values received but not read yet, without blocking.
+    pub fn drain(&mut self) -> Vec<i64> {
+        self.input.try_iter().collect()
+    }
+}
+
+impl Io for ChanIo {
+    fn read(&mut self) -> Result<Option<i64>> {
+        match self.timeout {
+            None => Ok(self.input.recv().ok()),
+            Some(timeout) => match self.input.recv_timeout(timeout) {
+                Ok(value) => Ok(Some(value)),
+                Err(RecvTimeoutError::Disconnected) => Ok(None),
+                Err(RecvTimeoutError::Timeout) => Err(ChanError::Timeout)?,
+            },
+        }
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        let res = match &self.output {
+            None => Err(ChanError::Disconnected),
+            Some(Output::Unbounded(tx)) => tx.send(value).map_err(|_| ChanError::Disconnected),
+            Some(Output::Bounded(tx)) => tx.send(value).map_err(|_| ChanError::Disconnected),
+        };
+        Ok(res?)
+    }
+}
+
+/// Runs `cpu` on a new thread, closing its output when it stops.
+///
+/// Returns the stopped machine along with why it stopped, so that its memory
+/// and any unread input can be inspected.
+pub fn spawn<M>(mut cpu: IntCode<ChanIo, M>) -> JoinHandle<SpawnResult<M>>
+where
+    M: Memory + Send + 'static,
+{
+    thread::spawn(move || {
+        let res = cpu.run();
+        cpu.io.close();
+        res.map(|status| (status, cpu))
+    })
+}
+
+pub type SpawnResult<M> = std::result::Result<(Status, IntCode<ChanIo, M>), IntcodeError>;
+
+#[test]
+fn test_chan() {
+    use std::sync::mpsc::{channel, sync_channel};
+
+    // The day7 feedback loop, one amplifier per thread.
+    let prog = vec![
+        3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28,
+        1005, 28, 6, 99, 0, 0, 5,
+    ];
+    let phases = [9, 8, 7, 6, 5];
+    let (mut txs, rxs): (Vec<_>, Vec<_>) = (0..phases.len()).map(|_| sync_channel(2)).unzip();
+    for (tx, &phase) in txs.iter().zip(phases.iter()) {
+        tx.send(phase).unwrap();
+    }
+    txs[0].send(0).unwrap();
+    txs.rotate_left(1);
+    let amps = rxs
+        .into_iter()
+        .zip(txs)
+        .map(|(rx, tx)| spawn(IntCode::new(ChanIo::bounded(rx, tx), prog.clone())))
+        .collect::<Vec<_>>();
+    let mut amps = amps
+        .into_iter()
+        .map(|it| it.join().unwrap().unwrap().1)
+        .collect::<Vec<_>>();
+    assert_eq!(amps[0].io.drain(), vec![139629729]);
+
+    // Readers see the end of the stream once their peer halts.
+    let (tx, rx) = channel();
+    let (out, _rx) = channel();
+    let first = spawn(IntCode::new(ChanIo::new(channel().1, tx), vec![104, 1, 99]));
+    let second = spawn(IntCode::new(ChanIo::new(rx, out), vec![3, 0, 3, 0, 99]));
+    first.join().unwrap().unwrap();
+    let (status, cpu) = second.join().unwrap().unwrap();
+    assert_eq!(status, Status::AwaitingInput);
+    assert_eq!(cpu.ip(), 2);
+    assert_eq!(cpu.mem()[0], 1);
+
+    // Reads time out.
+    let (_tx, rx) = channel();
+    let io = ChanIo::new(rx, channel().0).with_timeout(Duration::from_millis(10));
+    let err = IntCode::new(io, vec![3, 0, 99]).run().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "io error: timed out waiting for input (ip = 0, rb = 0)"
+    );
+}
@@ -0,0 +1,435 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::{
+    disasm::{self, Item},
+    Event, IntCode, Io, Memory, Op, Result, Trace,
+};
+
+/// Why the debugger handed control back to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Step,
+    Breakpoint(i64),
+    Watchpoint {
+        addr: i64,
+        old: i64,
+        new: i64,
+    },
+    Halted,
+    /// The machine needs input and its `Io` has none left.
+    AwaitingInput,
+    /// Reverse execution reached the oldest recorded instruction.
+    HistoryStart,
+}
+
+pub struct Debugger<IO, M = Vec<i64>> {
+    pub cpu: IntCode<IO, M>,
+    breakpoints: BTreeSet<i64>,
+    watchpoints: BTreeMap<i64, i64>,
+    recent: VecDeque<i64>,
+    history: VecDeque<Undo>,
+    history_limit: usize,
+    /// Inputs and outputs already exchanged with the `Io` by instructions
+    /// that were reversed. Re-executing them replays the inputs and doesn't
+    /// repeat the outputs.
+    replay_inputs: Vec<i64>,
+    replay_outputs: usize,
+}
+
+const RECENT: usize = 8;
+
+/// Everything needed to revert a single instruction.
+#[derive(Debug, Default, Clone, Copy)]
+struct Undo {
+    ip: i64,
+    bp: i64,
+    write: Option<(i64, i64)>,
+    input: Option<i64>,
+    output: bool,
+}
+
+impl Trace for Undo {
+    fn instr(&mut self, ip: i64, bp: i64, _op: &Op) {
+        *self = Undo {
+            ip,
+            bp,
+            ..Undo::default()
+        }
+    }
+    fn store(&mut self, addr: i64, old: i64, _new: i64) {
+        self.write = Some((addr, old))
+    }
+    fn input(&mut self, value: i64) {
+        self.input = Some(value)
+    }
+    fn output(&mut self, _value: i64) {
+        self.output = true
+    }
+}
+
+impl<IO: Io, M: Memory> Debugger<IO, M> {
+    pub fn new(cpu: IntCode<IO, M>) -> Debugger<IO, M> {
+        Debugger {
+            cpu,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            recent: VecDeque::new(),
+            history: VecDeque::new(),
+            history_limit: 1 << 20,
+            replay_inputs: Vec::new(),
+            replay_outputs: 0,
+        }
+    }
+
+    /// Sets how many instructions can be reversed. Each costs a few dozen
+    /// bytes.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history_limit = limit;
+        while self.history.len() > limit {
+            self.history.pop_front();
+        }
+    }
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+    /// Forgets the recorded history, for when the state was changed behind
+    /// the debugger's back.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = i64> + '_ {
+        self.breakpoints.iter().copied()
+    }
+    pub fn add_breakpoint(&mut self, addr: i64) -> bool {
+        self.breakpoints.insert(addr)
+    }
+    pub fn remove_breakpoint(&mut self, addr: i64) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = i64> + '_ {
+        self.watchpoints.keys().copied()
+    }
+    pub fn add_watchpoint(&mut self, addr: i64) -> Result<bool> {
+        let value = self.peek(addr)?;
+        Ok(self.watchpoints.insert(addr, value).is_none())
+    }
+    pub fn remove_watchpoint(&mut self, addr: i64) -> bool {
+        self.watchpoints.remove(&addr).is_some()
+    }
+
+    pub fn peek(&self, addr: i64) -> Result<i64> {
+        let res = self
+            .cpu
+            .mem()
+            .cell(addr as usize)
+            .ok_or_else(|| format!("invalid addr: {}", addr))?;
+        Ok(res)
+    }
+    pub fn set_ip(&mut self, ip: i64) {
+        self.cpu.set_ip(ip);
+        self.clear_history();
+    }
+    pub fn set_bp(&mut self, bp: i64) {
+        self.cpu.set_bp(bp);
+        self.clear_history();
+    }
+
+    /// Modifies a memory cell. This can't be reversed and clears the history.
+    pub fn poke(&mut self, addr: i64, value: i64) -> Result<()> {
+        let cell = self
+            .cpu
+            .mem_mut()
+            .cell_mut(addr as usize)
+            .ok_or_else(|| format!("invalid addr: {}", addr))?;
+        *cell = value;
+        // Changes made by the user shouldn't trigger watchpoints.
+        if let Some(old) = self.watchpoints.get_mut(&addr) {
+            *old = value;
+        }
+        self.clear_history();
+        Ok(())
+    }
+
+    /// Executes a single instruction, ignoring breakpoints.
+    pub fn step(&mut self) -> Result<Stop> {
+        let ip = self.cpu.ip();
+        let mut undo = Undo::default();
+        match self.cpu.exec(&mut undo)? {
+            None => (),
+            Some(Event::NeedsInput) => {
+                let value = match self.replay_inputs.pop() {
+                    Some(it) => it,
+                    None => match self.cpu.io.read().map_err(|e| self.cpu.io_error(ip, e))? {
+                        Some(it) => it,
+                        None => return Ok(Stop::AwaitingInput),
+                    },
+                };
+                self.cpu.provide_input(value)?;
+                self.cpu.exec(&mut undo)?;
+            }
+            Some(Event::Output(value)) => {
+                if self.replay_outputs > 0 {
+                    self.replay_outputs -= 1;
+                } else {
+                    self.cpu
+                        .io
+                        .write(value)
+                        .map_err(|e| self.cpu.io_error(ip, e))?;
+                }
+            }
+            Some(Event::Halted) => return Ok(Stop::Halted),
+        }
+        if self.history.len() == self.history_limit {
+            self.history.pop_front();
+        }
+        if self.history_limit > 0 {
+            self.history.push_back(undo);
+        }
+        if self.recent.len() == RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(ip);
+        Ok(self.check_watchpoints())
+    }
+
+    /// Reverts the last executed instruction.
+    pub fn reverse_step(&mut self) -> Result<Stop> {
+        let undo = match self.history.pop_back() {
+            Some(it) => it,
+            None => return Ok(Stop::HistoryStart),
+        };
+        if let Some((addr, old)) = undo.write {
+            *self.cpu.mem_mut().cell_mut(addr as usize).unwrap() = old;
+        }
+        if let Some(value) = undo.input {
+            self.replay_inputs.push(value);
+        }
+        if undo.output {
+            self.replay_outputs += 1;
+        }
+        self.cpu.set_ip(undo.ip);
+        self.cpu.set_bp(undo.bp);
+        self.recent.pop_back();
+        Ok(self.check_watchpoints())
+    }
+
+    /// Runs backwards until a breakpoint, a watchpoint or the start of the
+    /// history.
+    pub fn reverse_cont(&mut self) -> Result<Stop> {
+        loop {
+            match self.reverse_step()? {
+                Stop::Step => (),
+                stop => return Ok(stop),
+            }
+            let ip = self.cpu.ip();
+            if self.breakpoints.contains(&ip) {
+                return Ok(Stop::Breakpoint(ip));
+            }
+        }
+    }
+
+    /// Runs backwards to the instruction which last wrote to `addr`, leaving
+    /// the machine just before that write.
+    pub fn reverse_to_write(&mut self, addr: i64) -> Result<Stop> {
+        loop {
+            let wrote = match self.history.back() {
+                Some(undo) => undo.write.map(|it| it.0) == Some(addr),
+                None => return Ok(Stop::HistoryStart),
+            };
+            let stop = self.reverse_step()?;
+            if wrote {
+                return Ok(stop);
+            }
+        }
+    }
+
+    fn check_watchpoints(&mut self) -> Stop {
+        for (&addr, old) in self.watchpoints.iter_mut() {
+            let new = self.cpu.mem().cell(addr as usize).unwrap();
+            if *old != new {
+                let res = Stop::Watchpoint {
+                    addr,
+                    old: *old,
+                    new,
+                };
+                *old = new;
+                return res;
+            }
+        }
+        Stop::Step
+    }
+
+    /// Runs until a breakpoint, a watchpoint or halt.
+    pub fn cont(&mut self) -> Result<Stop> {
+        self.run_until(|_| false)
+    }
+
+    /// Steps over the current instruction.
+    ///
+    /// Intcode has no call instruction, so this runs until the machine comes
+    /// back to the instruction following the current one with the same
+    /// relative base, which covers the usual `[rb+..]` call/return idiom.
+    pub fn step_over(&mut self) -> Result<Stop> {
+        let (size, _) = self.cpu.decode()?;
+        let ret = (self.cpu.ip() + size, self.cpu.bp());
+        self.run_until(|cpu| (cpu.ip(), cpu.bp()) == ret)
+    }
+
+    fn run_until(&mut self, done: impl Fn(&IntCode<IO, M>) -> bool) -> Result<Stop> {
+        loop {
+            match self.step()? {
+                Stop::Step => (),
+                stop => return Ok(stop),
+            }
+            if done(&self.cpu) {
+                return Ok(Stop::Step);
+            }
+            let ip = self.cpu.ip();
+            if self.breakpoints.contains(&ip) {
+                return Ok(Stop::Breakpoint(ip));
+            }
+        }
+    }
+
+    /// Disassembles the recently executed instructions followed by `after`
+    /// instructions starting at `ip`.
+    pub fn context(&self, after: usize) -> Vec<Item> {
+        let mem = self.cpu.mem();
+        let mut res = self
+            .recent
+            .iter()
+            .filter_map(|&addr| disasm::item_at(mem, addr))
+            .collect::<Vec<_>>();
+        let mut addr = self.cpu.ip();
+        for _ in 0..after {
+            match disasm::item_at(mem, addr) {
+                Some(item) => {
+                    addr += item.size();
+                    res.push(item);
+                }
+                None => break,
+            }
+        }
+        res
+    }
+}
+
+#[test]
+fn test_debugger() {
+    use crate::{asm::assemble, MemIo};
+
+    let mem = assemble(
+        "
+                in -> [n]
+        loop:   add [n], #-1 -> [n]
+                add [acc], #1 -> [acc]
+                jt [n], #loop
+                out [acc]
+                hlt
+        n:      .data 0
+        acc:    .data 0
+        ",
+    )
+    .unwrap();
+    let n = 16;
+    let acc = 17;
+
+    let cpu = IntCode::new(MemIo::new(vec![3]), mem);
+    let mut dbg = Debugger::new(cpu);
+    dbg.add_breakpoint(10);
+    assert_eq!(dbg.cont().unwrap(), Stop::Breakpoint(10));
+    assert_eq!(dbg.peek(n).unwrap(), 2);
+
+    dbg.add_watchpoint(acc).unwrap();
+    assert_eq!(
+        dbg.cont().unwrap(),
+        Stop::Watchpoint {
+            addr: acc,
+            old: 1,
+            new: 2
+        }
+    );
+    assert_eq!(dbg.cpu.ip(), 10);
+    assert_eq!(dbg.step().unwrap(), Stop::Step);
+    assert_eq!(dbg.cpu.ip(), 2);
+
+    let context = dbg.context(2);
+    assert_eq!(
+        context.last().unwrap().to_string(),
+        "    6: add [17], #1 -> [17]"
+    );
+
+    dbg.remove_watchpoint(acc);
+    dbg.remove_breakpoint(10);
+    dbg.poke(n, 10).unwrap();
+    assert_eq!(dbg.cont().unwrap(), Stop::Halted);
+    assert_eq!(dbg.cpu.io.into_output(), vec![12]);
+}
+
+#[test]
+fn test_reverse() {
+    use crate::{asm::assemble, MemIo};
+
+    let mem = assemble(
+        "
+        loop:   in -> [x]
+                out [x]
+                add [acc], [x] -> [acc]
+                jt [x], #loop
+                hlt
+        x:      .data 0
+        acc:    .data 0
+        ",
+    )
+    .unwrap();
+    let x = 12;
+    let acc = 13;
+
+    let cpu = IntCode::new(MemIo::new(vec![1, 2, 3, 0]), mem);
+    let mut dbg = Debugger::new(cpu);
+    assert_eq!(dbg.cont().unwrap(), Stop::Halted);
+    assert_eq!(dbg.peek(acc).unwrap(), 6);
+
+    // The last `add` wrote 6 again, the one before accumulated 3.
+    assert_eq!(dbg.reverse_to_write(acc).unwrap(), Stop::Step);
+    assert_eq!(dbg.peek(x).unwrap(), 0);
+    assert_eq!(dbg.reverse_to_write(acc).unwrap(), Stop::Step);
+    assert_eq!(dbg.cpu.ip(), 4);
+    assert_eq!(dbg.peek(acc).unwrap(), 3);
+    assert_eq!(dbg.peek(x).unwrap(), 3);
+    assert_eq!(dbg.reverse_to_write(x).unwrap(), Stop::Step);
+    assert_eq!(dbg.cpu.ip(), 0);
+    assert_eq!(dbg.peek(x).unwrap(), 2);
+
+    dbg.add_breakpoint(2);
+    assert_eq!(dbg.reverse_cont().unwrap(), Stop::Breakpoint(2));
+    assert_eq!(dbg.peek(x).unwrap(), 2);
+    dbg.add_watchpoint(acc).unwrap();
+    assert_eq!(
+        dbg.reverse_cont().unwrap(),
+        Stop::Watchpoint {
+            addr: acc,
+            old: 1,
+            new: 0
+        }
+    );
+    assert_eq!(dbg.cpu.ip(), 4);
+
+    // Going forward replays the inputs instead of reading them again, and
+    // doesn't repeat the outputs.
+    dbg.remove_breakpoint(2);
+    dbg.remove_watchpoint(acc);
+    assert_eq!(dbg.cont().unwrap(), Stop::Halted);
+    assert_eq!(dbg.peek(acc).unwrap(), 6);
+    assert_eq!(dbg.cpu.io.into_output(), vec![1, 2, 3, 0]);
+
+    let cpu = IntCode::new(MemIo::new(vec![0]), vec![3, 5, 4, 5, 99, 0]);
+    let mut dbg = Debugger::new(cpu);
+    dbg.set_history_limit(1);
+    assert_eq!(dbg.cont().unwrap(), Stop::Halted);
+    assert_eq!(dbg.reverse_step().unwrap(), Stop::Step);
+    assert_eq!(dbg.reverse_step().unwrap(), Stop::HistoryStart);
+    assert_eq!(dbg.cpu.ip(), 2);
+}
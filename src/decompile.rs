@@ -0,0 +1,560 @@
+//! Decompilation of Intcode images to structured pseudo-Rust.
+//!
+//! Works on top of [`Cfg`]: natural loops become labeled `loop`s, conditional
+//! jumps become `if`s joining at their immediate post-dominator, and calls
+//! following the relative base convention become function calls. Whatever
+//! doesn't fit this structure is emitted as `goto`.
+//!
+//! Memory is `mem[A]`. Cells relative to the base are numbered from the
+//! function's frame: `s1` is the return address, `s2`, `s3`, ... are the
+//! arguments, followed by the locals.
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+};
+
+use crate::{
+    cfg::{constant, Block, Cfg, Exit, Function},
+    Addr, ArithOp, JumpOp, Op, Value,
+};
+
+pub fn decompile(image: &[i64]) -> String {
+    let cfg = Cfg::new(image);
+    let params = params(&cfg);
+    let mut res = String::new();
+    for (i, f) in cfg.functions.iter().enumerate() {
+        if i > 0 {
+            res += "\n";
+        }
+        res += &Decompiler::new(&cfg, f, &params).run();
+    }
+    res
+}
+
+/// Number of arguments passed to each function, by entry.
+fn params(cfg: &Cfg) -> HashMap<i64, usize> {
+    let mut res = HashMap::new();
+    for block in cfg.blocks.values() {
+        if let Exit::Call { target, .. } = block.exit {
+            let n = call_args(block).len();
+            let it = res.entry(target).or_insert(0);
+            *it = n.max(*it);
+        }
+    }
+    res
+}
+
+/// The stores into the callee's frame before a call, `[rb+2]`, `[rb+3]`, ...
+/// Returns their indices in `block.ops`.
+fn call_args(block: &Block) -> Vec<usize> {
+    let mut res = Vec::new();
+    for k in 2.. {
+        let dst = Addr {
+            value: k,
+            rel: true,
+        };
+        let idx = block.ops.iter().rposition(|(_, op)| match op {
+            Op::Arith { dst: it, .. } | Op::Input { dst: it } => *it == dst,
+            _ => false,
+        });
+        match idx {
+            Some(idx) if matches!(block.ops[idx].1, Op::Arith { .. }) => res.push(idx),
+            _ => return res,
+        }
+    }
+    unreachable!()
+}
+
+struct Decompiler<'a> {
+    cfg: &'a Cfg,
+    f: &'a Function,
+    params: &'a HashMap<i64, usize>,
+    /// Relative base at the start of each block, relative to its value on
+    /// entry to the function. `None` if it isn't known statically.
+    deltas: HashMap<i64, Option<i64>>,
+    ipdom: HashMap<i64, Option<i64>>,
+    /// Loop headers and the blocks following the loops.
+    loops: HashMap<i64, Option<i64>>,
+    active: Vec<i64>,
+    emitted: BTreeSet<i64>,
+    out: String,
+    indent: usize,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(cfg: &'a Cfg, f: &'a Function, params: &'a HashMap<i64, usize>) -> Decompiler<'a> {
+        let succs = f
+            .blocks
+            .iter()
+            .map(|&b| {
+                let succs = cfg.blocks[&b].succs();
+                (
+                    b,
+                    succs
+                        .into_iter()
+                        .filter(|it| f.blocks.contains(it))
+                        .collect(),
+                )
+            })
+            .collect::<BTreeMap<i64, Vec<i64>>>();
+        let mut preds = BTreeMap::<i64, Vec<i64>>::new();
+        for (&b, succs) in succs.iter() {
+            for &s in succs {
+                preds.entry(s).or_default().push(b);
+            }
+        }
+
+        let dom = dominators(&f.blocks, &[f.entry], &succs);
+        let exits = succs
+            .iter()
+            .filter(|(_, succs)| succs.is_empty())
+            .map(|(&b, _)| b)
+            .collect::<Vec<_>>();
+        let pdom = dominators(&f.blocks, &exits, &preds);
+        let ipdom = f.blocks.iter().map(|&b| (b, immediate(&pdom, b))).collect();
+
+        let mut loops = HashMap::new();
+        for (&b, next) in succs.iter() {
+            for &header in next.iter().filter(|it| dom[&b].contains(it)) {
+                let body = natural_loop(header, b, &preds);
+                let exit = match immediate(&pdom, header) {
+                    Some(it) if !body.contains(&it) => Some(it),
+                    _ => body
+                        .iter()
+                        .flat_map(|it| succs[it].iter())
+                        .find(|it| !body.contains(it))
+                        .copied(),
+                };
+                loops.entry(header).or_insert(exit);
+            }
+        }
+
+        let mut res = Decompiler {
+            cfg,
+            f,
+            params,
+            deltas: HashMap::new(),
+            ipdom,
+            loops,
+            active: Vec::new(),
+            emitted: BTreeSet::new(),
+            out: String::new(),
+            indent: 0,
+        };
+        res.compute_deltas();
+        res
+    }
+
+    fn compute_deltas(&mut self) {
+        let mut work = vec![(self.f.entry, Some(0))];
+        while let Some((b, delta)) = work.pop() {
+            if self.deltas.contains_key(&b) || !self.f.blocks.contains(&b) {
+                continue;
+            }
+            self.deltas.insert(b, delta);
+            let block = &self.cfg.blocks[&b];
+            let mut delta = delta;
+            for (_, op) in block.ops.iter() {
+                if let Op::AdjBp { adj } = op {
+                    delta = match (delta, adj) {
+                        (Some(d), Value::Immediate(adj)) => d.checked_add(*adj),
+                        _ => None,
+                    };
+                }
+            }
+            work.extend(block.succs().into_iter().map(|it| (it, delta)));
+        }
+    }
+
+    fn run(mut self) -> String {
+        let name = fn_name(self.f.entry);
+        let n = self.params.get(&self.f.entry).copied().unwrap_or(0);
+        let params = (0..n)
+            .map(|i| format!("s{}: i64", i + 2))
+            .collect::<Vec<_>>();
+        self.line(&format!("fn {}({}) {{", name, params.join(", ")));
+        self.indent += 1;
+        self.emit(self.f.entry, None);
+        self.indent -= 1;
+        self.line("}");
+        self.out
+    }
+
+    fn line(&mut self, text: &str) {
+        writeln!(self.out, "{:indent$}{}", "", text, indent = self.indent * 4).unwrap();
+    }
+
+    fn emit(&mut self, mut b: i64, until: Option<i64>) {
+        loop {
+            if Some(b) == until {
+                return;
+            }
+            if self.active.contains(&b) {
+                self.line(&format!("continue 'b{};", b));
+                return;
+            }
+            let exit_of = self
+                .active
+                .iter()
+                .rev()
+                .find(|it| self.loops[it] == Some(b))
+                .copied();
+            if let Some(header) = exit_of {
+                self.line(&format!("break 'b{};", header));
+                return;
+            }
+            if !self.cfg.blocks.contains_key(&b) {
+                self.line(&format!("goto b{}; // falls off the end", b));
+                return;
+            }
+            if !self.emitted.insert(b) {
+                self.line(&format!("goto b{};", b));
+                return;
+            }
+
+            if let Some(&exit) = self.loops.get(&b) {
+                self.line(&format!("'b{}: loop {{", b));
+                self.indent += 1;
+                self.active.push(b);
+                if let Some(next) = self.block(b) {
+                    self.emit(next, None);
+                }
+                self.active.pop();
+                self.indent -= 1;
+                self.line("}");
+                match exit {
+                    Some(exit) => b = exit,
+                    None => return,
+                }
+                continue;
+            }
+            match self.block(b) {
+                Some(next) => b = next,
+                None => return,
+            }
+        }
+    }
+
+    /// Emits the statements of `b` and any branches, returning where the
+    /// code continues.
+    fn block(&mut self, b: i64) -> Option<i64> {
+        let block = &self.cfg.blocks[&b];
+        let args = match block.exit {
+            Exit::Call { .. } => call_args(block),
+            _ => Vec::new(),
+        };
+        let mut arg_values = vec![String::new(); args.len()];
+        let mut delta = self.deltas[&b];
+        for (idx, &(_, op)) in block.ops.iter().enumerate() {
+            match op {
+                Op::Halt => self.line("halt();"),
+                Op::Arith { dst, .. } => {
+                    if let Some(pos) = args.iter().position(|&it| it == idx) {
+                        arg_values[pos] = expr(&op, delta);
+                        continue;
+                    }
+                    let is_ret_addr = match block.exit {
+                        Exit::Call { ret, .. } => {
+                            dst == Addr {
+                                value: 1,
+                                rel: true,
+                            } && constant(&op) == Some(ret)
+                        }
+                        _ => false,
+                    };
+                    if !is_ret_addr {
+                        self.line(&assign(&place(dst, delta), &op, delta));
+                    }
+                }
+                Op::Input { dst } => self.line(&format!("{} = input();", place(dst, delta))),
+                Op::Output { src } => self.line(&format!("output({});", value(src, delta))),
+                Op::AdjBp { adj } => {
+                    let prologue = b == self.f.entry && idx == 0;
+                    let epilogue =
+                        matches!(block.exit, Exit::Return { .. }) && idx + 2 == block.ops.len();
+                    if !prologue && !epilogue {
+                        self.line(&format!("rb += {};", value(adj, delta)));
+                    }
+                    delta = match (delta, adj) {
+                        (Some(d), Value::Immediate(adj)) => d.checked_add(adj),
+                        _ => None,
+                    };
+                }
+                Op::Jump { .. } if matches!(block.exit, Exit::Call { .. }) => (),
+                Op::Jump { op, cond, tgt } => return self.exit(b, op, cond, tgt, delta),
+            }
+        }
+        match block.exit {
+            Exit::Call { target, ret } => {
+                self.line(&format!("{}({});", fn_name(target), arg_values.join(", ")));
+                Some(ret)
+            }
+            Exit::Fall(next) => Some(next),
+            _ => None,
+        }
+    }
+
+    fn exit(
+        &mut self,
+        b: i64,
+        op: JumpOp,
+        cond: Value,
+        tgt: Value,
+        delta: Option<i64>,
+    ) -> Option<i64> {
+        let test = |negate: bool| {
+            let eq = (op == JumpOp::IfFalse) != negate;
+            format!("{} {} 0", value(cond, delta), if eq { "==" } else { "!=" })
+        };
+        match self.cfg.blocks[&b].exit {
+            Exit::Fall(next) => Some(next),
+            Exit::Call { .. } => unreachable!(),
+            Exit::Halt => None,
+            Exit::Jump { taken, fall: None } => Some(taken),
+            Exit::Return { fall: None } => {
+                self.line("return;");
+                None
+            }
+            Exit::Indirect { fall: None } => {
+                self.line(&format!("jump({});", value(tgt, delta)));
+                None
+            }
+            Exit::Return { fall: Some(fall) } => {
+                self.line(&format!("if {} {{", test(false)));
+                self.line("    return;");
+                self.line("}");
+                Some(fall)
+            }
+            Exit::Indirect { fall: Some(fall) } => {
+                self.line(&format!("if {} {{", test(false)));
+                self.line(&format!("    jump({});", value(tgt, delta)));
+                self.line("}");
+                Some(fall)
+            }
+            Exit::Jump {
+                taken,
+                fall: Some(fall),
+            } => {
+                let join = self.ipdom[&b];
+                let (first, second, negate) = if join == Some(taken) {
+                    (fall, None, true)
+                } else if join == Some(fall) {
+                    (taken, None, false)
+                } else {
+                    (taken, Some(fall), false)
+                };
+                self.line(&format!("if {} {{", test(negate)));
+                self.indent += 1;
+                self.emit(first, join);
+                self.indent -= 1;
+                if let Some(second) = second {
+                    self.line("} else {");
+                    self.indent += 1;
+                    self.emit(second, join);
+                    self.indent -= 1;
+                }
+                self.line("}");
+                join
+            }
+        }
+    }
+}
+
+fn fn_name(entry: i64) -> String {
+    match entry {
+        0 => "main".to_string(),
+        _ => format!("f{}", entry),
+    }
+}
+
+fn place(addr: Addr, delta: Option<i64>) -> String {
+    match (addr.rel, delta) {
+        (false, _) => format!("mem[{}]", addr.value),
+        (true, Some(d)) => match d.checked_add(addr.value) {
+            Some(slot) if slot > 0 => format!("s{}", slot),
+            _ => format!("rb[{}]", addr.value),
+        },
+        (true, None) => format!("rb[{}]", addr.value),
+    }
+}
+
+fn value(value: Value, delta: Option<i64>) -> String {
+    match value {
+        Value::Immediate(it) => it.to_string(),
+        Value::Addr(addr) => place(addr, delta),
+    }
+}
+
+fn expr(op: &Op, delta: Option<i64>) -> String {
+    let (op, lhs, rhs) = match *op {
+        Op::Arith { op, lhs, rhs, .. } => (op, lhs, rhs),
+        _ => unreachable!(),
+    };
+    let (l, r) = (value(lhs, delta), value(rhs, delta));
+    match (op, lhs, rhs) {
+        (ArithOp::Add, Value::Immediate(0), _) => r,
+        (ArithOp::Add, _, Value::Immediate(0)) => l,
+        (ArithOp::Add, _, Value::Immediate(it)) if it < 0 => format!("{} - {}", l, -it),
+        (ArithOp::Add, ..) => format!("{} + {}", l, r),
+        (ArithOp::Mul, Value::Immediate(1), _) => r,
+        (ArithOp::Mul, _, Value::Immediate(1)) => l,
+        (ArithOp::Mul, ..) => format!("{} * {}", l, r),
+        (ArithOp::LessThan, ..) => format!("({} < {}) as i64", l, r),
+        (ArithOp::Equals, ..) => format!("({} == {}) as i64", l, r),
+    }
+}
+
+fn assign(dst: &str, op: &Op, delta: Option<i64>) -> String {
+    if let Op::Arith {
+        op: arith,
+        lhs: Value::Addr(lhs),
+        rhs,
+        ..
+    } = *op
+    {
+        if place(lhs, delta) == dst {
+            match (arith, rhs) {
+                (ArithOp::Add, Value::Immediate(it)) if it < 0 => {
+                    return format!("{} -= {};", dst, -it)
+                }
+                (ArithOp::Add, _) => return format!("{} += {};", dst, value(rhs, delta)),
+                (ArithOp::Mul, _) => return format!("{} *= {};", dst, value(rhs, delta)),
+                _ => (),
+            }
+        }
+    }
+    format!("{} = {};", dst, expr(op, delta))
+}
+
+/// Iterative dominator sets, for paths from `roots` along `edges`.
+fn dominators(
+    nodes: &BTreeSet<i64>,
+    roots: &[i64],
+    edges: &BTreeMap<i64, Vec<i64>>,
+) -> HashMap<i64, BTreeSet<i64>> {
+    let mut into = BTreeMap::<i64, Vec<i64>>::new();
+    for (&from, tos) in edges.iter() {
+        for &to in tos {
+            into.entry(to).or_default().push(from);
+        }
+    }
+    let mut res = nodes
+        .iter()
+        .map(|&n| {
+            let set = if roots.contains(&n) {
+                std::iter::once(n).collect()
+            } else {
+                nodes.clone()
+            };
+            (n, set)
+        })
+        .collect::<HashMap<_, _>>();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &n in nodes.iter().filter(|it| !roots.contains(it)) {
+            let mut set: Option<BTreeSet<i64>> = None;
+            for p in into.get(&n).into_iter().flatten() {
+                set = Some(match set {
+                    None => res[p].clone(),
+                    Some(set) => set.intersection(&res[p]).copied().collect(),
+                });
+            }
+            let mut set = set.unwrap_or_default();
+            set.insert(n);
+            if set != res[&n] {
+                res.insert(n, set);
+                changed = true;
+            }
+        }
+    }
+    res
+}
+
+/// The closest strict dominator of `n`.
+fn immediate(dom: &HashMap<i64, BTreeSet<i64>>, n: i64) -> Option<i64> {
+    let size = dom[&n].len();
+    dom[&n]
+        .iter()
+        .find(|&&d| d != n && dom[&d].len() + 1 == size)
+        .copied()
+}
+
+fn natural_loop(header: i64, tail: i64, preds: &BTreeMap<i64, Vec<i64>>) -> BTreeSet<i64> {
+    let mut res = BTreeSet::new();
+    res.insert(header);
+    let mut work = vec![tail];
+    while let Some(n) = work.pop() {
+        if res.insert(n) {
+            work.extend(preds.get(&n).into_iter().flatten());
+        }
+    }
+    res
+}
+
+#[test]
+fn test_decompile() {
+    use crate::asm::assemble;
+
+    let prog = assemble(
+        "
+                arb #100
+                in -> [n]
+        loop:   lt [n], #1 -> [t]
+                jt [t], #done
+                add [n], #0 -> [rb+2]
+                add #ret, #0 -> [rb+1]
+                jt #1, #square
+        ret:    out [rb+2]
+                add [n], #-1 -> [n]
+                jt #1, #loop
+        done:   hlt
+        square: arb #3
+                mul [rb-1], [rb-1] -> [rb-1]
+                lt [rb-1], #50 -> [rb]
+                jf [rb], #big
+                out #0
+        big:    arb #-3
+                jt #1, [rb+1]
+        n:      .data 0
+        t:      .data 0
+        ",
+    )
+    .unwrap();
+    let expected = "\
+fn main() {
+    mem[52] = input();
+    'b4: loop {
+        mem[53] = (mem[52] < 1) as i64;
+        if mem[53] == 0 {
+            f32(mem[52]);
+            output(s102);
+            mem[52] -= 1;
+            continue 'b4;
+        }
+        break 'b4;
+    }
+    halt();
+}
+
+fn f32(s2: i64) {
+    s2 *= s2;
+    s3 = (s2 < 50) as i64;
+    if s3 != 0 {
+        output(0);
+    }
+    return;
+}
+";
+    assert_eq!(decompile(&prog), expected);
+
+    // Code running past the end of the image.
+    assert_eq!(
+        decompile(&[104, 1]),
+        "fn main() {\n    output(1);\n    goto b2; // falls off the end\n}\n"
+    );
+    assert_eq!(
+        decompile(&[109, i64::MAX, 109, 1, 1201, 5, 0, 0, 99]),
+        "fn main() {\n    rb += 1;\n    mem[0] = rb[5];\n    halt();\n}\n"
+    );
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+use crate::{Memory, Op};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Item {
+    Instr { addr: i64, size: i64, op: Op },
+    Data { addr: i64, value: i64 },
+}
+
+impl Item {
+    pub fn addr(&self) -> i64 {
+        match *self {
+            Item::Instr { addr, .. } | Item::Data { addr, .. } => addr,
+        }
+    }
+
+    pub fn size(&self) -> i64 {
+        match *self {
+            Item::Instr { size, .. } => size,
+            Item::Data { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Instr { addr, op, .. } => write!(f, "{:>5}: {}", addr, op),
+            Item::Data { addr, value } => write!(f, "{:>5}: .data {}", addr, value),
+        }
+    }
+}
+
+/// Decodes a single item at `addr`, falling back to `.data` for cells which
+/// don't hold a valid instruction.
+pub fn item_at(mem: &(impl Memory + ?Sized), addr: i64) -> Option<Item> {
+    let value = mem.cell(addr as usize)?;
+    let load = |addr: i64| mem.cell(addr as usize);
+    let res = match Op::decode(addr, 0, load) {
+        Ok((size, op)) => Item::Instr { addr, size, op },
+        Err(_) => Item::Data { addr, value },
+    };
+    Some(res)
+}
+
+/// Linearly sweeps the memory image from the start.
+pub fn disassemble(mem: &[i64]) -> Vec<Item> {
+    let mut res = Vec::new();
+    let mut addr = 0;
+    while let Some(item) = item_at(mem, addr) {
+        addr += item.size();
+        res.push(item);
+    }
+    res
+}
+
+pub fn listing(mem: &[i64]) -> String {
+    let mut res = String::new();
+    for item in disassemble(mem) {
+        res += &format!("{}\n", item);
+    }
+    res
+}
+
+#[test]
+fn test_disasm() {
+    let mem = [
+        1101, 12, 5, 3, 1201, 12, -1, 3, 1005, 3, 0, 4, 3, 204, 7, 109, -2, 99, 1234,
+    ];
+    let expected = "    0: add #12, #5 -> [3]
+    4: add [rb+12], #-1 -> [3]
+    8: jt [3], #0
+   11: out [3]
+   13: out [rb+7]
+   15: arb #-2
+   17: hlt
+   18: .data 1234
+";
+    assert_eq!(listing(&mem), expected);
+
+    // Operands running past the end of memory are data, too.
+    let mem = [3, 0, 1, 0];
+    let items = disassemble(&mem);
+    assert_eq!(items[0].to_string(), "    0: in -> [0]");
+    assert_eq!(items[1], Item::Data { addr: 2, value: 1 });
+    assert_eq!(items.len(), 3);
+}
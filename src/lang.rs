@@ -0,0 +1,871 @@
+//! A tiny language compiled to Intcode.
+//!
+//! ```text
+//! fn square(x) {
+//!     return x * x;
+//! }
+//!
+//! fn main() {
+//!     let n = input();
+//!     while n > 0 {
+//!         output(square(n));
+//!         n = n - 1;
+//!     }
+//! }
+//! ```
+//!
+//! All values are integers. Expressions support `+ - *`, comparisons, which
+//! evaluate to 0 or 1, unary `-` and `!`, calls and `input()`. Execution
+//! starts at `main` and halts when it returns.
+//!
+//! The compiler emits assembly for [`crate::asm`]. Functions use the
+//! relative base calling convention recognised by [`crate::cfg`]: the caller
+//! stores the return address at `[rb+1]` and the arguments from `[rb+2]` on,
+//! the callee allocates its frame with `arb` and leaves the result in its
+//! first argument slot.
+use std::{collections::HashMap, fmt};
+
+use crate::asm;
+
+#[derive(Debug)]
+pub struct CompileError {
+    pub line: usize,
+    pub col: usize,
+    pub msg: String,
+}
+
+impl std::error::Error for CompileError {}
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.msg)
+    }
+}
+
+pub fn compile(text: &str) -> Result<Vec<i64>, CompileError> {
+    let asm = compile_to_asm(text)?;
+    let res = asm::assemble(&asm).unwrap_or_else(|e| panic!("invalid assembly: {}\n{}", e, asm));
+    Ok(res)
+}
+
+pub fn compile_to_asm(text: &str) -> Result<String, CompileError> {
+    let tokens = tokenize(text)?;
+    let funcs = Parser { tokens, idx: 0 }.program()?;
+    codegen(&funcs)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pos {
+    line: usize,
+    col: usize,
+}
+
+impl Pos {
+    fn error(self, msg: impl Into<String>) -> CompileError {
+        CompileError {
+            line: self.line,
+            col: self.col,
+            msg: msg.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Punct(&'static str),
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(it) => write!(f, "`{}`", it),
+            Token::Int(it) => write!(f, "`{}`", it),
+            Token::Punct(it) => write!(f, "`{}`", it),
+            Token::Eof => write!(f, "end of file"),
+        }
+    }
+}
+
+const PUNCTS: &[&str] = &[
+    "==", "!=", "<=", ">=", "(", ")", "{", "}", ",", ";", "=", "+", "-", "*", "<", ">", "!",
+];
+
+fn tokenize(text: &str) -> Result<Vec<(Pos, Token)>, CompileError> {
+    let mut res = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let mut rest = line;
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() || rest.starts_with("//") {
+                break;
+            }
+            let pos = Pos {
+                line: idx + 1,
+                col: line[..line.len() - rest.len()].chars().count() + 1,
+            };
+            let c = rest.chars().next().unwrap();
+            let len = if c.is_ascii_digit() {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                let value = rest[..len]
+                    .parse()
+                    .map_err(|_| pos.error(format!("invalid number: {}", &rest[..len])))?;
+                res.push((pos, Token::Int(value)));
+                len
+            } else if c.is_alphabetic() || c == '_' {
+                let len = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                res.push((pos, Token::Ident(rest[..len].to_string())));
+                len
+            } else {
+                match PUNCTS.iter().find(|it| rest.starts_with(*it)) {
+                    Some(punct) => {
+                        res.push((pos, Token::Punct(punct)));
+                        punct.len()
+                    }
+                    None => Err(pos.error(format!("unexpected character: {:?}", c)))?,
+                }
+            };
+            rest = &rest[len..];
+        }
+    }
+    let eof = Pos {
+        line: text.lines().count() + 1,
+        col: 1,
+    };
+    res.push((eof, Token::Eof));
+    Ok(res)
+}
+
+#[derive(Debug)]
+struct Func {
+    pos: Pos,
+    name: String,
+    params: Vec<String>,
+    body: Vec<Stmt>,
+}
+
+#[derive(Debug)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(Pos, String, Expr),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Return(Option<Expr>),
+    Output(Expr),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Int(i64),
+    Var(Pos, String),
+    Input,
+    Call(Pos, String, Vec<Expr>),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+struct Parser {
+    tokens: Vec<(Pos, Token)>,
+    idx: usize,
+}
+
+impl Parser {
+    fn pos(&self) -> Pos {
+        self.tokens[self.idx].0
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.idx].1
+    }
+
+    fn bump(&mut self) -> Token {
+        let res = self.peek().clone();
+        if res != Token::Eof {
+            self.idx += 1;
+        }
+        res
+    }
+
+    fn unexpected(&self, expected: &str) -> CompileError {
+        self.pos()
+            .error(format!("expected {}, found {}", expected, self.peek()))
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        match self.peek() {
+            Token::Punct(it) if *it == punct => {
+                self.idx += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), CompileError> {
+        if !self.eat(punct) {
+            Err(self.unexpected(&format!("`{}`", punct)))?
+        }
+        Ok(())
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Ident(it) if it == keyword)
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        match self.peek() {
+            Token::Ident(it) => {
+                let res = it.clone();
+                self.bump();
+                Ok(res)
+            }
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    fn program(&mut self) -> Result<Vec<Func>, CompileError> {
+        let mut res = Vec::new();
+        while *self.peek() != Token::Eof {
+            if !self.at_keyword("fn") {
+                Err(self.unexpected("`fn`"))?
+            }
+            self.bump();
+            let pos = self.pos();
+            let name = self.ident()?;
+            self.expect("(")?;
+            let mut params = Vec::new();
+            while !self.eat(")") {
+                params.push(self.ident()?);
+                if !self.eat(",") {
+                    self.expect(")")?;
+                    break;
+                }
+            }
+            let body = self.block()?;
+            res.push(Func {
+                pos,
+                name,
+                params,
+                body,
+            });
+        }
+        Ok(res)
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.expect("{")?;
+        let mut res = Vec::new();
+        while !self.eat("}") {
+            res.push(self.stmt()?);
+        }
+        Ok(res)
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, CompileError> {
+        let pos = self.pos();
+        let keyword = match self.peek() {
+            Token::Ident(it) => it.clone(),
+            _ => String::new(),
+        };
+        let res = match keyword.as_str() {
+            "let" => {
+                self.bump();
+                let name = self.ident()?;
+                self.expect("=")?;
+                Stmt::Let(name, self.expr()?)
+            }
+            "if" => {
+                self.bump();
+                return self.if_stmt();
+            }
+            "while" => {
+                self.bump();
+                let cond = self.expr()?;
+                return Ok(Stmt::While(cond, self.block()?));
+            }
+            "return" => {
+                self.bump();
+                match self.peek() {
+                    Token::Punct(";") => Stmt::Return(None),
+                    _ => Stmt::Return(Some(self.expr()?)),
+                }
+            }
+            "output" => {
+                self.bump();
+                self.expect("(")?;
+                let value = self.expr()?;
+                self.expect(")")?;
+                Stmt::Output(value)
+            }
+            _ if matches!(self.tokens.get(self.idx + 1), Some((_, Token::Punct("=")))) => {
+                let name = self.ident()?;
+                self.bump();
+                Stmt::Assign(pos, name, self.expr()?)
+            }
+            _ => Stmt::Expr(self.expr()?),
+        };
+        self.expect(";")?;
+        Ok(res)
+    }
+
+    fn if_stmt(&mut self) -> Result<Stmt, CompileError> {
+        let cond = self.expr()?;
+        let then = self.block()?;
+        let mut els = Vec::new();
+        if self.at_keyword("else") {
+            self.bump();
+            if self.at_keyword("if") {
+                self.bump();
+                els.push(self.if_stmt()?);
+            } else {
+                els = self.block()?;
+            }
+        }
+        Ok(Stmt::If(cond, then, els))
+    }
+
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        let lhs = self.sum()?;
+        let op = match self.peek() {
+            Token::Punct("<") => BinOp::Lt,
+            Token::Punct(">") => BinOp::Gt,
+            Token::Punct("<=") => BinOp::Le,
+            Token::Punct(">=") => BinOp::Ge,
+            Token::Punct("==") => BinOp::Eq,
+            Token::Punct("!=") => BinOp::Ne,
+            _ => return Ok(lhs),
+        };
+        self.bump();
+        let rhs = self.sum()?;
+        Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn sum(&mut self) -> Result<Expr, CompileError> {
+        let mut res = self.product()?;
+        loop {
+            let op = match self.peek() {
+                Token::Punct("+") => BinOp::Add,
+                Token::Punct("-") => BinOp::Sub,
+                _ => return Ok(res),
+            };
+            self.bump();
+            res = Expr::Binary(op, Box::new(res), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, CompileError> {
+        let mut res = self.unary()?;
+        while self.eat("*") {
+            res = Expr::Binary(BinOp::Mul, Box::new(res), Box::new(self.unary()?));
+        }
+        Ok(res)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        if self.eat("-") {
+            return Ok(match self.unary()? {
+                Expr::Int(it) => Expr::Int(-it),
+                it => Expr::Neg(Box::new(it)),
+            });
+        }
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let pos = self.pos();
+        if !matches!(
+            self.peek(),
+            Token::Int(_) | Token::Ident(_) | Token::Punct("(")
+        ) {
+            Err(self.unexpected("expression"))?
+        }
+        match self.bump() {
+            Token::Int(it) => Ok(Expr::Int(it)),
+            Token::Punct("(") => {
+                let res = self.expr()?;
+                self.expect(")")?;
+                Ok(res)
+            }
+            Token::Ident(name) => {
+                if !self.eat("(") {
+                    return Ok(Expr::Var(pos, name));
+                }
+                let mut args = Vec::new();
+                while !self.eat(")") {
+                    args.push(self.expr()?);
+                    if !self.eat(",") {
+                        self.expect(")")?;
+                        break;
+                    }
+                }
+                if name == "input" {
+                    if !args.is_empty() {
+                        Err(pos.error("`input` takes no arguments"))?
+                    }
+                    return Ok(Expr::Input);
+                }
+                Ok(Expr::Call(pos, name, args))
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// An operand, before the frame layout is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Opnd {
+    Imm(i64),
+    Label(String),
+    /// Cell `N` of the current frame, counting from the return address.
+    Slot(i64),
+    /// Cell `N` of the frame of the function being called.
+    Callee(i64),
+}
+
+enum Line {
+    Label(String),
+    Instr(&'static str, Vec<Opnd>),
+}
+
+const MAIN: &str = "main";
+
+fn codegen(funcs: &[Func]) -> Result<String, CompileError> {
+    let mut arities = HashMap::new();
+    for f in funcs {
+        if arities.insert(f.name.as_str(), f.params.len()).is_some() {
+            Err(f.pos.error(format!("duplicate function: {}", f.name)))?
+        }
+    }
+    match funcs.iter().find(|it| it.name == MAIN) {
+        None => Err(Pos { line: 1, col: 1 }.error("no `main` function"))?,
+        Some(f) if !f.params.is_empty() => Err(f.pos.error("`main` takes no parameters"))?,
+        Some(_) => (),
+    }
+
+    let mut res = String::new();
+    res += "        arb #stack\n";
+    res += "        add #exit, #0 -> [rb+1]\n";
+    res += "        jt #1, #fn_main\n";
+    res += "exit:   hlt\n";
+    let mut labels = 0;
+    for f in funcs {
+        let mut gen = FnGen {
+            arities: &arities,
+            lines: Vec::new(),
+            scopes: vec![HashMap::new()],
+            next_slot: 2,
+            max_slot: 2,
+            labels: &mut labels,
+        };
+        res += &gen.func(f)?;
+    }
+    res += "stack:  .data 0\n";
+    Ok(res)
+}
+
+struct FnGen<'a> {
+    arities: &'a HashMap<&'a str, usize>,
+    lines: Vec<Line>,
+    scopes: Vec<HashMap<String, i64>>,
+    next_slot: i64,
+    max_slot: i64,
+    labels: &'a mut usize,
+}
+
+impl FnGen<'_> {
+    fn func(&mut self, f: &Func) -> Result<String, CompileError> {
+        for param in f.params.iter() {
+            let slot = self.alloc();
+            self.scopes[0].insert(param.clone(), slot);
+        }
+        let ret = self.label();
+        self.block(&f.body, &ret)?;
+
+        let frame = self.max_slot;
+        let slot = |s: i64| match s - frame {
+            0 => "[rb]".to_string(),
+            off => format!("[rb{:+}]", off),
+        };
+        let render = |opnd: &Opnd| match opnd {
+            Opnd::Imm(it) => format!("#{}", it),
+            Opnd::Label(it) => format!("#{}", it),
+            Opnd::Slot(s) => slot(*s),
+            Opnd::Callee(s) => format!("[rb+{}]", s),
+        };
+
+        let mut res = format!("fn_{}:\n", f.name);
+        res += &format!("        arb #{}\n", frame);
+        for line in self.lines.iter() {
+            match line {
+                Line::Label(it) => res += &format!("{}:\n", it),
+                Line::Instr(mnemonic, opnds) => {
+                    let opnds = opnds.iter().map(render).collect::<Vec<_>>();
+                    let text = match *mnemonic {
+                        "add" | "mul" | "lt" | "eq" => {
+                            format!("{}, {} -> {}", opnds[0], opnds[1], opnds[2])
+                        }
+                        "in" => format!("-> {}", opnds[0]),
+                        _ => opnds.join(", "),
+                    };
+                    res += &format!("        {} {}\n", mnemonic, text);
+                }
+            }
+        }
+        res += &format!("{}:\n", ret);
+        res += &format!("        arb #-{}\n", frame);
+        res += "        jt #1, [rb+1]\n";
+        Ok(res)
+    }
+
+    fn label(&mut self) -> String {
+        *self.labels += 1;
+        format!(".L{}", self.labels)
+    }
+
+    fn alloc(&mut self) -> i64 {
+        let res = self.next_slot;
+        self.next_slot += 1;
+        self.max_slot = self.max_slot.max(res);
+        res
+    }
+
+    fn emit(&mut self, mnemonic: &'static str, opnds: Vec<Opnd>) {
+        self.lines.push(Line::Instr(mnemonic, opnds))
+    }
+
+    fn mov(&mut self, src: Opnd, dst: Opnd) {
+        if src != dst {
+            self.emit("add", vec![src, Opnd::Imm(0), dst])
+        }
+    }
+
+    fn lookup(&self, pos: Pos, name: &str) -> Result<Opnd, CompileError> {
+        match self.scopes.iter().rev().find_map(|it| it.get(name)) {
+            Some(&slot) => Ok(Opnd::Slot(slot)),
+            None => Err(pos.error(format!("undefined variable: {}", name))),
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt], ret: &str) -> Result<(), CompileError> {
+        self.scopes.push(HashMap::new());
+        for stmt in stmts {
+            self.stmt(stmt, ret)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, ret: &str) -> Result<(), CompileError> {
+        // Temporaries only live during a statement.
+        let temps = self.next_slot;
+        match stmt {
+            Stmt::Let(name, value) => {
+                let slot = self.alloc();
+                self.expr_to(value, Opnd::Slot(slot))?;
+                self.scopes.last_mut().unwrap().insert(name.clone(), slot);
+                return Ok(());
+            }
+            Stmt::Assign(pos, name, value) => {
+                let dst = self.lookup(*pos, name)?;
+                self.expr_to(value, dst)?;
+            }
+            Stmt::If(cond, then, els) => {
+                let (else_label, end) = (self.label(), self.label());
+                let cond = self.expr(cond)?;
+                self.next_slot = temps;
+                self.emit("jf", vec![cond, Opnd::Label(else_label.clone())]);
+                self.block(then, ret)?;
+                if !els.is_empty() {
+                    self.emit("jt", vec![Opnd::Imm(1), Opnd::Label(end.clone())]);
+                }
+                self.lines.push(Line::Label(else_label));
+                self.block(els, ret)?;
+                self.lines.push(Line::Label(end));
+            }
+            Stmt::While(cond, body) => {
+                let (top, end) = (self.label(), self.label());
+                self.lines.push(Line::Label(top.clone()));
+                let cond = self.expr(cond)?;
+                self.next_slot = temps;
+                self.emit("jf", vec![cond, Opnd::Label(end.clone())]);
+                self.block(body, ret)?;
+                self.emit("jt", vec![Opnd::Imm(1), Opnd::Label(top)]);
+                self.lines.push(Line::Label(end));
+            }
+            Stmt::Return(value) => {
+                if let Some(value) = value {
+                    self.expr_to(value, Opnd::Slot(2))?;
+                }
+                self.emit("jt", vec![Opnd::Imm(1), Opnd::Label(ret.to_string())]);
+            }
+            Stmt::Output(value) => {
+                let value = self.expr(value)?;
+                self.emit("out", vec![value]);
+            }
+            Stmt::Expr(value) => {
+                self.expr(value)?;
+            }
+        }
+        self.next_slot = temps;
+        Ok(())
+    }
+
+    /// Evaluates `expr` into an operand, using a temporary if needed.
+    fn expr(&mut self, expr: &Expr) -> Result<Opnd, CompileError> {
+        match expr {
+            Expr::Int(it) => Ok(Opnd::Imm(*it)),
+            Expr::Var(pos, name) => self.lookup(*pos, name),
+            _ => {
+                let res = Opnd::Slot(self.alloc());
+                self.expr_to(expr, res.clone())?;
+                Ok(res)
+            }
+        }
+    }
+
+    fn expr_to(&mut self, expr: &Expr, dst: Opnd) -> Result<(), CompileError> {
+        match expr {
+            Expr::Int(_) | Expr::Var(..) => {
+                let src = self.expr(expr)?;
+                self.mov(src, dst);
+            }
+            Expr::Input => self.emit("in", vec![dst]),
+            Expr::Call(pos, name, args) => {
+                match self.arities.get(name.as_str()) {
+                    None => Err(pos.error(format!("undefined function: {}", name)))?,
+                    Some(&n) if n != args.len() => Err(pos.error(format!(
+                        "`{}` takes {} arguments, got {}",
+                        name,
+                        n,
+                        args.len()
+                    )))?,
+                    Some(_) => (),
+                }
+                // Arguments are evaluated first, as calls in them would
+                // overwrite the callee's frame.
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.expr(arg)?);
+                }
+                for (i, value) in values.into_iter().enumerate() {
+                    self.mov(value, Opnd::Callee(i as i64 + 2));
+                }
+                let ret = self.label();
+                self.mov(Opnd::Label(ret.clone()), Opnd::Callee(1));
+                self.emit(
+                    "jt",
+                    vec![Opnd::Imm(1), Opnd::Label(format!("fn_{}", name))],
+                );
+                self.lines.push(Line::Label(ret));
+                self.mov(Opnd::Callee(2), dst);
+            }
+            Expr::Neg(value) => {
+                let value = self.expr(value)?;
+                self.emit("mul", vec![value, Opnd::Imm(-1), dst]);
+            }
+            Expr::Not(value) => {
+                let value = self.expr(value)?;
+                self.emit("eq", vec![value, Opnd::Imm(0), dst]);
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                let (mnemonic, a, b, negate) = match op {
+                    BinOp::Add => ("add", lhs, rhs, false),
+                    BinOp::Mul => ("mul", lhs, rhs, false),
+                    BinOp::Sub => {
+                        let rhs = match rhs {
+                            Opnd::Imm(it) => Opnd::Imm(-it),
+                            rhs => {
+                                let tmp = Opnd::Slot(self.alloc());
+                                self.emit("mul", vec![rhs, Opnd::Imm(-1), tmp.clone()]);
+                                tmp
+                            }
+                        };
+                        ("add", lhs, rhs, false)
+                    }
+                    BinOp::Lt => ("lt", lhs, rhs, false),
+                    BinOp::Gt => ("lt", rhs, lhs, false),
+                    BinOp::Le => ("lt", rhs, lhs, true),
+                    BinOp::Ge => ("lt", lhs, rhs, true),
+                    BinOp::Eq => ("eq", lhs, rhs, false),
+                    BinOp::Ne => ("eq", lhs, rhs, true),
+                };
+                self.emit(mnemonic, vec![a, b, dst.clone()]);
+                if negate {
+                    self.emit("eq", vec![dst.clone(), Opnd::Imm(0), dst]);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_compile() {
+    use crate::{IntCode, MemIo};
+
+    fn run(text: &str, input: Vec<i64>) -> Vec<i64> {
+        let prog = compile(text).unwrap();
+        let mut cpu = IntCode::new(MemIo::new(input), prog);
+        cpu.run().unwrap();
+        cpu.io.into_output()
+    }
+
+    let squares = "
+        fn square(x) {
+            return x * x;
+        }
+
+        fn main() {
+            let n = input();
+            while n > 0 {
+                output(square(n));
+                n = n - 1;
+            }
+        }
+    ";
+    assert_eq!(run(squares, vec![3]), vec![9, 4, 1]);
+    assert_eq!(run(squares, vec![0]), vec![]);
+
+    let arith = "
+        fn main() {
+            let a = input();
+            let b = input();
+            output(a + b);
+            output(a - b);
+            output(a * b);
+            output(-a + 2 * (b - 1));
+            output(a < b);
+            output(a > b);
+            output(a <= b);
+            output(a >= b);
+            output(a == b);
+            output(a != b);
+            output(!a);
+        }
+    ";
+    assert_eq!(
+        run(arith, vec![7, 3]),
+        vec![10, 4, 21, -3, 0, 1, 0, 1, 0, 1, 0]
+    );
+    assert_eq!(
+        run(arith, vec![3, 3]),
+        vec![6, 0, 9, 1, 0, 0, 1, 1, 1, 0, 0]
+    );
+    assert_eq!(
+        run(arith, vec![0, 5]),
+        vec![5, -5, 0, 8, 1, 0, 1, 0, 0, 1, 1]
+    );
+
+    // Recursion, nested calls in arguments and `else if` chains.
+    let calls = "
+        fn fib(n) {
+            if n < 2 {
+                return n;
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+
+        fn add3(a, b, c) {
+            return a + b + c;
+        }
+
+        fn sign(x) {
+            if x < 0 {
+                return -1;
+            } else if x == 0 {
+                return 0;
+            } else {
+                return 1;
+            }
+        }
+
+        fn main() {
+            output(fib(input()));
+            output(add3(fib(5), add3(1, 2, 3), fib(6)));
+            let x = input();
+            while x != 3 {
+                output(sign(x));
+                x = x + 1;
+            }
+        }
+    ";
+    assert_eq!(run(calls, vec![10, -1]), vec![55, 19, -1, 0, 1, 1]);
+
+    // Scopes and shadowing.
+    let scopes = "
+        fn main() {
+            let x = 1;
+            if x {
+                let x = 2;
+                output(x);
+            }
+            output(x);
+            let x = x + 10;
+            output(x);
+            output(input() * input());
+        }
+    ";
+    assert_eq!(run(scopes, vec![6, 7]), vec![2, 1, 11, 42]);
+
+    // The static analyses understand the generated calling convention.
+    let prog = compile(calls).unwrap();
+    let cfg = crate::cfg::Cfg::new(&prog);
+    assert_eq!(cfg.functions.len(), 5);
+    assert_eq!(cfg.indirect_jumps().count(), 0);
+}
+
+#[test]
+fn test_compile_errors() {
+    fn check(text: &str, expected: &str) {
+        let err = compile(text).unwrap_err();
+        assert_eq!(err.to_string(), expected);
+    }
+
+    check("fn main() { output(x); }", "1:20: undefined variable: x");
+    check("fn main() { f(); }", "1:13: undefined function: f");
+    check(
+        "fn f(a) { }\nfn main() { f(1, 2); }",
+        "2:13: `f` takes 1 arguments, got 2",
+    );
+    check("fn f() { }", "1:1: no `main` function");
+    check("fn main(x) { }", "1:4: `main` takes no parameters");
+    check(
+        "fn main() { }\nfn main() { }",
+        "2:4: duplicate function: main",
+    );
+    check(
+        "fn main() { let = 1; }",
+        "1:17: expected identifier, found `=`",
+    );
+    check("fn main() { output(1) }", "1:23: expected `;`, found `}`");
+    check("fn main() {", "2:1: expected expression, found end of file");
+    check(
+        "fn main() { x = 1 # 2; }",
+        "1:19: unexpected character: '#'",
+    );
+}
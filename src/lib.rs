@@ -1,10 +1,31 @@
 use std::{
     cell::Cell,
+    collections::{BTreeMap, VecDeque},
+    convert::TryFrom,
     fmt,
     io::{self, Read, Write},
     iter, mem, ops,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
+pub mod adapt;
+pub mod asm;
+pub mod cfg;
+pub mod chan;
+pub mod debugger;
+pub mod decompile;
+pub mod disasm;
+pub mod lang;
+pub mod net;
+pub mod profile;
+pub mod tape;
+pub mod topology;
+pub mod trace;
+pub mod transpile;
+
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -146,17 +167,18 @@ pub fn read_stdin_to_string() -> Result<String, io::Error> {
 }
 
 pub fn parse_memory(text: &str) -> Result<Vec<i64>> {
-    let mut res = text
+    let res = text
         .trim()
         .split(',')
         .map(|it| it.trim().parse::<i64>())
         .collect::<Result<Vec<_>, _>>()?;
-    extend_memory(&mut res);
     Ok(res)
 }
 
 pub trait Io {
-    fn read(&mut self) -> Result<i64>;
+    /// Returns `None` at the end of the input. The machine then stops in
+    /// [`Status::AwaitingInput`], and reads again when resumed.
+    fn read(&mut self) -> Result<Option<i64>>;
     fn write(&mut self, value: i64) -> Result<()>;
 }
 
@@ -175,11 +197,16 @@ impl StdIo {
 }
 
 impl Io for StdIo {
-    fn read(&mut self) -> Result<i64> {
+    fn read(&mut self) -> Result<Option<i64>> {
         let mut buf = String::new();
-        self.stdin.read_line(&mut buf)?;
-        let res = buf.trim().parse()?;
-        Ok(res)
+        if self.stdin.read_line(&mut buf)? == 0 {
+            return Ok(None);
+        }
+        let line = buf.trim();
+        let res = line
+            .parse()
+            .map_err(|e| format!("invalid input {:?}: {}", line, e))?;
+        Ok(Some(res))
     }
     fn write(&mut self, value: i64) -> Result<()> {
         writeln!(self.stdout, "{}", value)?;
@@ -187,41 +214,143 @@ impl Io for StdIo {
     }
 }
 
-pub struct AsciiIo {
-    stdio: StdIo,
+/// Text `Io`: outputs are printed as characters, inputs are read a line at
+/// a time.
+///
+/// Lines come from the replies of the rules, then from the script, then from
+/// stdin unless the io was made with [`AsciiIo::script`].
+pub struct AsciiIo<W = io::Stdout> {
+    stdin: Option<io::Stdin>,
+    out: W,
     buf: Vec<u8>,
+    script: VecDeque<String>,
+    replies: VecDeque<String>,
+    rules: Vec<(String, String)>,
+    echo: bool,
+    /// Output since the last line was sent, matched against the rules.
+    screen: String,
 }
 
 impl AsciiIo {
     pub fn new() -> AsciiIo {
         AsciiIo {
-            stdio: StdIo::new(),
+            stdin: Some(io::stdin()),
+            out: io::stdout(),
             buf: Vec::new(),
+            script: VecDeque::new(),
+            replies: VecDeque::new(),
+            rules: Vec::new(),
+            echo: false,
+            screen: String::new(),
+        }
+    }
+    /// Reads input only from `script`, see [`AsciiIo::with_script`].
+    pub fn script(script: &str) -> AsciiIo {
+        let mut res = AsciiIo::new().with_script(script);
+        res.stdin = None;
+        res
+    }
+}
+
+impl<W: Write> AsciiIo<W> {
+    /// Queues the lines of `script`, which are sent as they are except for:
+    ///
+    /// - `# comment` lines, which are skipped,
+    /// - `!when X => Y` lines, which add a rule as [`AsciiIo::with_rule`].
+    pub fn with_script(mut self, script: &str) -> Self {
+        for line in script.lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+            match line
+                .strip_prefix("!when ")
+                .and_then(|it| it.split_once(" => "))
+            {
+                Some((when, send)) => self.rules.push((when.to_string(), send.to_string())),
+                None => self.script.push_back(line.to_string()),
+            }
+        }
+        self
+    }
+    /// Sends the line `send` whenever the output since the last line sent
+    /// contains `when`. Replies are sent before the rest of the script.
+    pub fn with_rule(mut self, when: &str, send: &str) -> Self {
+        self.rules.push((when.to_string(), send.to_string()));
+        self
+    }
+    /// Prints the lines sent from the script or by rules, as if typed in.
+    pub fn with_echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+    pub fn with_output<O: Write>(self, out: O) -> AsciiIo<O> {
+        AsciiIo {
+            stdin: self.stdin,
+            out,
+            buf: self.buf,
+            script: self.script,
+            replies: self.replies,
+            rules: self.rules,
+            echo: self.echo,
+            screen: self.screen,
+        }
+    }
+    pub fn into_output(self) -> W {
+        self.out
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>> {
+        if let Some(line) = self.replies.pop_front().or_else(|| self.script.pop_front()) {
+            if self.echo {
+                writeln!(self.out, "{}", line)?;
+            }
+            return Ok(Some(line + "\n"));
+        }
+        let stdin = match &self.stdin {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+        let mut buf = String::new();
+        if stdin.read_line(&mut buf)? == 0 {
+            return Ok(None);
         }
+        Ok(Some(buf))
     }
 }
 
-impl Io for AsciiIo {
-    fn read(&mut self) -> Result<i64> {
+impl<W: Write> Io for AsciiIo<W> {
+    fn read(&mut self) -> Result<Option<i64>> {
         if self.buf.is_empty() {
-            let buf = mem::take(&mut self.buf);
-            let mut buf = String::from_utf8(buf).unwrap();
-            self.stdio.stdin.read_line(&mut buf)?;
-            self.buf = buf.into_bytes();
+            let line = match self.next_line()? {
+                Some(it) => it,
+                None => return Ok(None),
+            };
+            self.screen.clear();
+            self.buf = line.into_bytes();
             self.buf.reverse();
         }
-        Ok(self.buf.pop().unwrap().into())
+        Ok(self.buf.pop().map(i64::from))
     }
     fn write(&mut self, value: i64) -> Result<()> {
         if value <= 128 {
-            write!(self.stdio.stdout, "{}", value as u8 as char)
+            write!(self.out, "{}", value as u8 as char)?;
+            self.screen.push(value as u8 as char);
         } else {
-            writeln!(self.stdio.stdout, "non-ASCII: {}", value)
-        }?;
+            writeln!(self.out, "non-ASCII: {}", value)?;
+        }
+        let rule = self
+            .rules
+            .iter()
+            .find(|(when, _)| self.screen.contains(when.as_str()));
+        if let Some((_, send)) = rule {
+            self.replies.push_back(send.clone());
+            self.screen.clear();
+        }
         Ok(())
     }
 }
 
+#[derive(Clone)]
 pub struct MemIo {
     input: Vec<i64>,
     output: Vec<i64>,
@@ -236,15 +365,17 @@ impl MemIo {
         }
     }
 
+    pub fn output(&self) -> &[i64] {
+        &self.output
+    }
     pub fn into_output(self) -> Vec<i64> {
         self.output
     }
 }
 
 impl Io for MemIo {
-    fn read(&mut self) -> Result<i64> {
-        let res = self.input.pop().ok_or("EOF")?;
-        Ok(res)
+    fn read(&mut self) -> Result<Option<i64>> {
+        Ok(self.input.pop())
     }
     fn write(&mut self, value: i64) -> Result<()> {
         self.output.push(value);
@@ -260,10 +391,10 @@ pub struct SlotIo {
 }
 
 impl Io for &SlotIo {
-    fn read(&mut self) -> Result<i64> {
+    fn read(&mut self) -> Result<Option<i64>> {
         let res = self.get();
         self.read.set(true);
-        Ok(res)
+        Ok(Some(res))
     }
     fn write(&mut self, value: i64) -> Result<()> {
         self.set(value);
@@ -291,132 +422,933 @@ impl SlotIo {
     }
 }
 
-pub struct IntCode<'a, IO> {
+/// Storage for the machine's memory.
+///
+/// Implemented both for owned images, which make [`IntCode`] cloneable, and
+/// for borrowed ones. Growable memories read zeros past their end and grow on
+/// writes, up to `VEC_MEM_LIMIT` cells for a `Vec` and `MEM_LIMIT` cells for
+/// [`PagedMemory`].
+pub trait Memory {
+    fn cell(&self, addr: usize) -> Option<i64>;
+    fn cell_mut(&mut self, addr: usize) -> Option<&mut i64>;
+}
+
+impl Memory for [i64] {
+    fn cell(&self, addr: usize) -> Option<i64> {
+        self.get(addr).copied()
+    }
+    fn cell_mut(&mut self, addr: usize) -> Option<&mut i64> {
+        self.get_mut(addr)
+    }
+}
+
+impl Memory for Vec<i64> {
+    fn cell(&self, addr: usize) -> Option<i64> {
+        if addr >= VEC_MEM_LIMIT {
+            return None;
+        }
+        Some(self.get(addr).copied().unwrap_or(0))
+    }
+    fn cell_mut(&mut self, addr: usize) -> Option<&mut i64> {
+        if addr >= VEC_MEM_LIMIT {
+            return None;
+        }
+        if addr >= self.len() {
+            self.resize(addr + 1, 0);
+        }
+        self.get_mut(addr)
+    }
+}
+
+pub const MEM_LIMIT: usize = 1 << 30;
+/// A `Vec` grows in one piece, so a stray write must not allocate gigabytes.
+pub const VEC_MEM_LIMIT: usize = 1 << 20;
+
+const PAGE_SIZE: usize = 1024;
+type Page = [i64; PAGE_SIZE];
+
+/// Copy-on-write memory split into fixed-size pages.
+///
+/// Clones share all pages until they are written to, and [`reset`] restores
+/// only the pages touched since the last reset. This makes it cheap to run a
+/// program many times from the same image.
+///
+/// [`reset`]: PagedMemory::reset
+#[derive(Clone)]
+pub struct PagedMemory {
+    pages: Vec<Option<Arc<Page>>>,
+    pristine: Arc<Vec<Option<Arc<Page>>>>,
+    dirty: Vec<usize>,
+}
+
+impl PagedMemory {
+    pub fn new(image: &[i64]) -> PagedMemory {
+        let pages = image
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                Some(Arc::new(page))
+            })
+            .collect::<Vec<_>>();
+        PagedMemory {
+            pristine: Arc::new(pages.clone()),
+            pages,
+            dirty: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for idx in self.dirty.drain(..) {
+            self.pages[idx] = self.pristine.get(idx).cloned().flatten();
+        }
+    }
+}
+
+impl Memory for PagedMemory {
+    fn cell(&self, addr: usize) -> Option<i64> {
+        if addr >= MEM_LIMIT {
+            return None;
+        }
+        let res = match self.pages.get(addr / PAGE_SIZE) {
+            Some(Some(page)) => page[addr % PAGE_SIZE],
+            _ => 0,
+        };
+        Some(res)
+    }
+    fn cell_mut(&mut self, addr: usize) -> Option<&mut i64> {
+        if addr >= MEM_LIMIT {
+            return None;
+        }
+        let idx = addr / PAGE_SIZE;
+        if idx >= self.pages.len() {
+            self.pages.resize(idx + 1, None);
+        }
+        let page = &mut self.pages[idx];
+        let shared = match page {
+            Some(it) => Arc::strong_count(it) > 1,
+            None => true,
+        };
+        if shared && !self.dirty.contains(&idx) {
+            self.dirty.push(idx);
+        }
+        let page = page.get_or_insert_with(|| Arc::new([0; PAGE_SIZE]));
+        Some(&mut Arc::make_mut(page)[addr % PAGE_SIZE])
+    }
+}
+
+impl<M: Memory + ?Sized> Memory for &mut M {
+    fn cell(&self, addr: usize) -> Option<i64> {
+        (**self).cell(addr)
+    }
+    fn cell_mut(&mut self, addr: usize) -> Option<&mut i64> {
+        (**self).cell_mut(addr)
+    }
+}
+
+/// Observer of the instructions executed by [`IntCode`].
+///
+/// Calls for a single instruction come in order: `instr`, then `arg` for every
+/// operand value the instruction reads, then `input`, `output` or `store` for
+/// its effects. `()` is the no-op tracer used by [`IntCode::run`].
+pub trait Trace {
+    fn instr(&mut self, _ip: i64, _bp: i64, _op: &Op) {}
+    fn arg(&mut self, _value: i64) {}
+    fn store(&mut self, _addr: i64, _old: i64, _new: i64) {}
+    fn input(&mut self, _value: i64) {}
+    fn output(&mut self, _value: i64) {}
+}
+
+impl Trace for () {}
+
+#[derive(Clone)]
+pub struct IntCode<IO, M = Vec<i64>> {
     pub io: IO,
-    mem: &'a mut [i64],
+    mem: M,
     ip: i64,
     bp: i64,
+    input: Option<i64>,
+    cache: Option<DecodeCache>,
+    overflow: Overflow,
+    /// Exact values of the cells holding widened results, along with the
+    /// truncated value stored in memory. Entries whose cell changed since
+    /// are stale.
+    wide: BTreeMap<i64, (i64, i128)>,
 }
 
-impl<'a, IO: Io> IntCode<'a, IO> {
-    pub fn new(io: IO, mem: &'a mut [i64]) -> IntCode<'a, IO> {
+/// Decoded instructions and their sizes, indexed by address. Shared between
+/// clones until one of them modifies it.
+type DecodeCache = Arc<Decoded>;
+
+/// Allocated in chunks of [`PAGE_SIZE`] addresses, for executed code only.
+#[derive(Clone, Default)]
+struct Decoded(Vec<Option<Box<DecodedPage>>>);
+
+type DecodedPage = [Option<(i64, Op)>; PAGE_SIZE];
+
+impl Decoded {
+    fn get(&self, ip: usize) -> Option<(i64, Op)> {
+        match self.0.get(ip / PAGE_SIZE) {
+            Some(Some(chunk)) => chunk[ip % PAGE_SIZE],
+            _ => None,
+        }
+    }
+    fn insert(&mut self, ip: usize, instr: (i64, Op)) {
+        let idx = ip / PAGE_SIZE;
+        if self.0.len() <= idx {
+            self.0.resize(idx + 1, None);
+        }
+        let chunk = self.0[idx].get_or_insert_with(|| Box::new([None; PAGE_SIZE]));
+        chunk[ip % PAGE_SIZE] = Some(instr);
+    }
+    fn remove(&mut self, ip: usize) {
+        if let Some(Some(chunk)) = self.0.get_mut(ip / PAGE_SIZE) {
+            chunk[ip % PAGE_SIZE] = None;
+        }
+    }
+}
+
+/// How arithmetic instructions treat results that don't fit in an `i64`,
+/// and relative base adjustments which overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Fault with [`IntcodeError::Overflow`], which holds the exact result.
+    #[default]
+    Checked,
+    /// Wrap around, like two's complement hardware would.
+    Wrapping,
+    /// Keep the exact result as an `i128`, see [`IntCode::exact`]. Arithmetic
+    /// instructions work on the exact values, other instructions fault when
+    /// they use one which doesn't fit in an `i64`, as does the relative base.
+    Widened,
+}
+
+/// Why [`IntCode::run`] or [`IntCode::run_with_budget`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Halted,
+    /// The machine is blocked on an input instruction and its `Io` has no
+    /// more input. Run it again to retry the read.
+    AwaitingInput,
+    /// The next instruction costs more than the remaining gas. Refill the
+    /// budget and run again to resume.
+    BudgetExhausted,
+    /// The budget's cancellation flag was set.
+    Cancelled,
+}
+
+/// Gas for [`IntCode::run_with_budget`], spent by every instruction executed.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    gas: u64,
+    costs: Option<Costs>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Budget {
+    pub fn new(gas: u64) -> Budget {
+        Budget {
+            gas,
+            ..Budget::default()
+        }
+    }
+    /// Charges instructions according to `costs`, instead of 1 each.
+    pub fn with_costs(mut self, costs: Costs) -> Budget {
+        self.costs = Some(costs);
+        self
+    }
+    /// Stops the machine, between two instructions, once `flag` is set.
+    pub fn with_cancel(mut self, flag: Arc<AtomicBool>) -> Budget {
+        self.cancel = Some(flag);
+        self
+    }
+    pub fn gas(&self) -> u64 {
+        self.gas
+    }
+    pub fn refill(&mut self, gas: u64) {
+        self.gas = self.gas.saturating_add(gas)
+    }
+
+    fn cancelled(&self) -> bool {
+        match &self.cancel {
+            Some(flag) => flag.load(Ordering::Relaxed),
+            None => false,
+        }
+    }
+    fn cost(&self, op: &Op) -> u64 {
+        match &self.costs {
+            Some(costs) => costs.get(op.op_code()).unwrap_or(0),
+            None => 1,
+        }
+    }
+}
+
+/// Cost of each instruction, by op code. Halting is free.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Costs([u64; 9]);
+
+impl Costs {
+    /// Every instruction costs `cost`.
+    pub fn uniform(cost: u64) -> Costs {
+        Costs([cost; 9])
+    }
+    /// Fails on invalid op codes. Setting the cost of halting (99) has no
+    /// effect.
+    pub fn set(mut self, op_code: i64, cost: u64) -> Result<Costs> {
+        if let Some(idx) = Costs::idx(op_code)? {
+            self.0[idx] = cost;
+        }
+        Ok(self)
+    }
+    /// `None` for invalid op codes.
+    pub fn get(&self, op_code: i64) -> Option<u64> {
+        match Costs::idx(op_code) {
+            Ok(Some(idx)) => Some(self.0[idx]),
+            Ok(None) => Some(0),
+            Err(_) => None,
+        }
+    }
+
+    /// `None` for halt.
+    fn idx(op_code: i64) -> Result<Option<usize>> {
+        match op_code {
+            1..=9 => Ok(Some(op_code as usize - 1)),
+            99 => Ok(None),
+            _ => Err(format!("invalid op code: {}", op_code))?,
+        }
+    }
+}
+
+/// Reason for [`IntCode::run_until_event`] to return control to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The machine is blocked on an input instruction, call
+    /// [`IntCode::provide_input`] to resume it.
+    NeedsInput,
+    Output(i64),
+    Halted,
+}
+
+impl<IO: Io, M: Memory> IntCode<IO, M> {
+    /// Runs until the machine halts, or needs input its `Io` doesn't have.
+    pub fn run(&mut self) -> Result<Status, IntcodeError> {
+        self.run_traced(&mut ())
+    }
+    /// Returns whether the machine can go on.
+    pub fn step(&mut self) -> Result<bool, IntcodeError> {
+        self.step_traced(&mut ())
+    }
+    pub fn run_traced(&mut self, trace: &mut impl Trace) -> Result<Status, IntcodeError> {
+        loop {
+            if let Some(status) = self.advance(trace)? {
+                return Ok(status);
+            }
+        }
+    }
+    /// Runs at most `n_steps` instructions.
+    pub fn run_for(&mut self, n_steps: u64) -> Result<Status, IntcodeError> {
+        self.run_with_budget(&mut Budget::new(n_steps))
+    }
+    /// Runs until the machine halts or `budget` runs out, whichever comes
+    /// first. An instruction is only executed if the remaining gas covers
+    /// its cost, so the run is deterministic and can be resumed.
+    pub fn run_with_budget(&mut self, budget: &mut Budget) -> Result<Status, IntcodeError> {
+        loop {
+            if budget.cancelled() {
+                return Ok(Status::Cancelled);
+            }
+            let (size, op) = self.fetch()?;
+            if op == Op::Halt {
+                return Ok(Status::Halted);
+            }
+            let cost = budget.cost(&op);
+            if cost > budget.gas {
+                return Ok(Status::BudgetExhausted);
+            }
+            if let Some(status) = self.advance_op(size, op, &mut ())? {
+                return Ok(status);
+            }
+            budget.gas -= cost;
+        }
+    }
+    pub fn step_traced(&mut self, trace: &mut impl Trace) -> Result<bool, IntcodeError> {
+        Ok(self.advance(trace)?.is_none())
+    }
+    /// Executes an instruction, returns why the machine stopped if it did.
+    pub(crate) fn advance(
+        &mut self,
+        trace: &mut impl Trace,
+    ) -> Result<Option<Status>, IntcodeError> {
+        let (size, op) = self.fetch()?;
+        self.advance_op(size, op, trace)
+    }
+    /// [`IntCode::advance`] with the instruction at `ip` already fetched.
+    fn advance_op(
+        &mut self,
+        size: i64,
+        op: Op,
+        trace: &mut impl Trace,
+    ) -> Result<Option<Status>, IntcodeError> {
+        let ip = self.ip;
+        match self.exec_op(size, op, trace)? {
+            None => (),
+            Some(Event::NeedsInput) => match self.io.read().map_err(|e| self.io_error(ip, e))? {
+                Some(value) => {
+                    self.provide_input(value)?;
+                    self.exec_op(size, op, trace)?;
+                }
+                None => return Ok(Some(Status::AwaitingInput)),
+            },
+            Some(Event::Output(value)) => self.io.write(value).map_err(|e| self.io_error(ip, e))?,
+            Some(Event::Halted) => return Ok(Some(Status::Halted)),
+        }
+        Ok(None)
+    }
+}
+
+impl<IO, M: Memory> IntCode<IO, M> {
+    pub fn new(io: IO, mem: M) -> IntCode<IO, M> {
         IntCode {
             io,
             mem,
             ip: 0,
             bp: 0,
+            input: None,
+            cache: None,
+            overflow: Overflow::default(),
+            wide: BTreeMap::new(),
+        }
+    }
+    /// Enables caching of decoded instructions.
+    ///
+    /// Writes through the machine evict the instructions they overlap, so
+    /// self-modifying code works as usual. Clones share the cache, which
+    /// makes it worthwhile to [`IntCode::predecode`] a machine which is then
+    /// cloned for every run.
+    pub fn with_cache(mut self) -> Self {
+        self.cache.get_or_insert_with(Default::default);
+        self
+    }
+    /// Sets the overflow policy, [`Overflow::Checked`] by default.
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+    /// The value of the cell at `addr`, or the exact result of the arithmetic
+    /// instruction which wrote it with [`Overflow::Widened`].
+    pub fn exact(&self, addr: i64) -> Option<i128> {
+        let value = self.load_instr(addr)?;
+        Some(self.widened(addr, value).unwrap_or(value as i128))
+    }
+    /// Fills the cache with the instructions found by a linear sweep through
+    /// `range`, enabling the cache if needed.
+    pub fn predecode(&mut self, range: ops::Range<i64>) {
+        let mut cache = Decoded::clone(self.cache.get_or_insert_with(Default::default));
+        let mut addr = range.start.max(0);
+        while addr < range.end {
+            match Op::decode(addr, self.bp, |it| self.load_instr(it)) {
+                Ok((size, op)) => {
+                    cache.insert(addr as usize, (size, op));
+                    addr += size;
+                }
+                Err(_) => addr += 1,
+            }
+        }
+        self.cache = Some(Arc::new(cache));
+    }
+    pub fn ip(&self) -> i64 {
+        self.ip
+    }
+    pub fn set_ip(&mut self, ip: i64) {
+        self.ip = ip
+    }
+    pub fn bp(&self) -> i64 {
+        self.bp
+    }
+    pub fn set_bp(&mut self, bp: i64) {
+        self.bp = bp
+    }
+    pub fn mem(&self) -> &M {
+        &self.mem
+    }
+    /// Direct access to memory. This drops any cached instructions, as
+    /// there's no telling which cells will change.
+    pub fn mem_mut(&mut self) -> &mut M {
+        if let Some(cache) = &mut self.cache {
+            *cache = Default::default();
+        }
+        &mut self.mem
+    }
+    pub fn into_mem(self) -> M {
+        self.mem
+    }
+
+    pub fn run_until_event(&mut self) -> Result<Event, IntcodeError> {
+        self.run_until_event_traced(&mut ())
+    }
+    pub fn run_until_event_traced(
+        &mut self,
+        trace: &mut impl Trace,
+    ) -> Result<Event, IntcodeError> {
+        loop {
+            if let Some(event) = self.exec(trace)? {
+                return Ok(event);
+            }
         }
     }
-    pub fn run(&mut self) -> Result<()> {
-        while self.step()? {}
+    /// Fails if the value provided before wasn't read yet.
+    pub fn provide_input(&mut self, value: i64) -> Result<(), IntcodeError> {
+        if self.input.is_some() {
+            Err(IntcodeError::InputPending {
+                ip: self.ip,
+                bp: self.bp,
+                value,
+            })?
+        }
+        self.input = Some(value);
         Ok(())
     }
-    pub fn step(&mut self) -> Result<bool> {
-        let (size, op) = self.decode()?;
+
+    /// Executes a single instruction. Input instructions without a provided
+    /// value, as well as halt, don't advance the machine.
+    fn exec(&mut self, trace: &mut impl Trace) -> Result<Option<Event>, IntcodeError> {
+        let (size, op) = self.fetch()?;
+        self.exec_op(size, op, trace)
+    }
+    /// [`IntCode::exec`] with the instruction at `ip` already fetched.
+    fn exec_op(
+        &mut self,
+        size: i64,
+        op: Op,
+        trace: &mut impl Trace,
+    ) -> Result<Option<Event>, IntcodeError> {
+        if let (Op::Input { .. }, None) = (op, self.input) {
+            return Ok(Some(Event::NeedsInput));
+        }
+        trace.instr(self.ip, self.bp, &op);
+        let mut res = None;
         match op {
-            Op::Halt => return Ok(false),
+            Op::Halt => return Ok(Some(Event::Halted)),
             Op::Arith { op, lhs, rhs, dst } => {
-                let lhs = self.decode_value(lhs)?;
-                let rhs = self.decode_value(rhs)?;
-                let res = op.eval(lhs, rhs);
-                self.store(dst, res)?;
+                let lhs = self.decode_exact(lhs, trace)?;
+                let rhs = self.decode_exact(rhs, trace)?;
+                let exact = op.eval(lhs, rhs).map_err(|it| self.overflow_error(it))?;
+                match self.narrow(exact) {
+                    Ok(value) => self.store(dst, value, trace)?,
+                    Err(_) if self.overflow == Overflow::Widened => {
+                        let value = exact as i64;
+                        self.store(dst, value, trace)?;
+                        let addr = self.decode_addr(dst)?;
+                        self.wide.insert(addr, (value, exact));
+                    }
+                    Err(err) => Err(err)?,
+                }
             }
             Op::Jump { op, cond, tgt } => {
-                let cond = self.decode_value(cond)?;
+                let cond = self.decode_value(cond, trace)?;
                 let jump = match op {
                     JumpOp::IfTrue => cond != 0,
                     JumpOp::IfFalse => cond == 0,
                 };
                 if jump {
-                    self.ip = self.decode_value(tgt)?;
-                    return Ok(true);
+                    self.ip = self.decode_value(tgt, trace)?;
+                    return Ok(None);
                 }
             }
             Op::Input { dst } => {
-                let res = self.io.read()?;
-                self.store(dst, res)?;
+                let value = self.input.take().unwrap();
+                trace.input(value);
+                self.store(dst, value, trace)?;
             }
             Op::Output { src } => {
-                let res = self.decode_value(src)?;
-                self.io.write(res)?;
+                let value = self.decode_value(src, trace)?;
+                trace.output(value);
+                res = Some(Event::Output(value));
             }
             Op::AdjBp { adj } => {
-                let adj = self.decode_value(adj)?;
-                self.bp += adj;
+                let adj = self.decode_value(adj, trace)?;
+                self.bp = self.narrow(self.bp as i128 + adj as i128)?;
             }
         }
         self.ip += size;
-        Ok(true)
+        Ok(res)
     }
 
-    fn decode_value(&self, value: Value) -> Result<i64> {
+    /// Loads a value which must fit in an `i64`.
+    fn decode_value(&self, value: Value, trace: &mut impl Trace) -> Result<i64, IntcodeError> {
+        let res = self.decode_exact(value, trace)?;
+        i64::try_from(res).map_err(|_| self.overflow_error(res))
+    }
+    /// Loads a value, widened if it holds the exact result of an arithmetic
+    /// instruction. Traces only see the truncated value.
+    fn decode_exact(&self, value: Value, trace: &mut impl Trace) -> Result<i128, IntcodeError> {
+        let res = match value {
+            Value::Immediate(it) => it,
+            Value::Addr(addr) => self.load(addr)?,
+        };
+        trace.arg(res);
         match value {
-            Value::Immediate(it) => Ok(it),
-            Value::Addr(addr) => self.load(addr),
+            Value::Addr(addr) if !self.wide.is_empty() => {
+                let addr = self.decode_addr(addr)?;
+                Ok(self.widened(addr, res).unwrap_or(res as i128))
+            }
+            _ => Ok(res as i128),
+        }
+    }
+    /// The exact value of the cell at `addr` holding `value`, if it was
+    /// widened.
+    fn widened(&self, addr: i64, value: i64) -> Option<i128> {
+        match self.wide.get(&addr) {
+            Some(&(truncated, exact)) if truncated == value => Some(exact),
+            _ => None,
         }
     }
-    fn decode_addr(&self, addr: Addr) -> Result<usize> {
-        let addr = if addr.rel {
-            self.bp + addr.value
+    fn decode_addr(&self, addr: Addr) -> Result<i64, IntcodeError> {
+        if addr.rel {
+            self.narrow(self.bp as i128 + addr.value as i128)
         } else {
-            addr.value
-        };
-        if !(0 <= addr && addr <= self.mem.len() as i64) {
-            Err(format!("invalid addr: {}", addr))?
+            Ok(addr.value)
         }
-        Ok(addr as usize)
     }
-    fn load(&self, addr: Addr) -> Result<i64> {
+    fn load(&self, addr: Addr) -> Result<i64, IntcodeError> {
         let addr = self.decode_addr(addr)?;
-        Ok(self.mem[addr])
-    }
-    fn load_instr(&self, addr: i64) -> Result<i64> {
-        self.load(Addr {
-            value: addr,
-            rel: false,
-        })
+        self.load_instr(addr).ok_or_else(|| self.invalid_addr(addr))
     }
-    fn store(&mut self, addr: Addr, value: i64) -> Result<()> {
+    fn load_instr(&self, addr: i64) -> Option<i64> {
+        if addr < 0 {
+            return None;
+        }
+        self.mem.cell(addr as usize)
+    }
+    fn store(
+        &mut self,
+        addr: Addr,
+        value: i64,
+        trace: &mut impl Trace,
+    ) -> Result<(), IntcodeError> {
         let addr = self.decode_addr(addr)?;
-        self.mem[addr] = value;
+        let cell = match addr {
+            _ if addr < 0 => None,
+            _ => self.mem.cell_mut(addr as usize),
+        };
+        let cell = match cell {
+            Some(it) => it,
+            None => return Err(self.invalid_addr(addr)),
+        };
+        let old = mem::replace(cell, value);
+        trace.store(addr, old, value);
+        if !self.wide.is_empty() {
+            self.wide.remove(&addr);
+        }
+        if let Some(cache) = &mut self.cache {
+            evict(cache, addr);
+        }
         Ok(())
     }
 
-    fn decode(&self) -> Result<(i64, Op)> {
-        fn to_value(modes: &mut i64, value: i64) -> Result<Value> {
+    fn decode(&self) -> Result<(i64, Op), IntcodeError> {
+        Op::decode(self.ip, self.bp, |addr| self.load_instr(addr))
+    }
+    /// Like `decode`, but goes through the cache, if enabled.
+    fn fetch(&mut self) -> Result<(i64, Op), IntcodeError> {
+        let idx = self.ip as usize;
+        match &self.cache {
+            None => return self.decode(),
+            Some(cache) => {
+                if let Some(it) = cache.get(idx) {
+                    return Ok(it);
+                }
+            }
+        }
+        let res = self.decode()?;
+        Arc::make_mut(self.cache.as_mut().unwrap()).insert(idx, res);
+        Ok(res)
+    }
+
+    /// Brings `value` back to an `i64` according to the overflow policy.
+    fn narrow(&self, value: i128) -> Result<i64, IntcodeError> {
+        match (i64::try_from(value), self.overflow) {
+            (Ok(it), _) => Ok(it),
+            (Err(_), Overflow::Wrapping) => Ok(value as i64),
+            (Err(_), _) => Err(self.overflow_error(value)),
+        }
+    }
+    fn overflow_error(&self, value: i128) -> IntcodeError {
+        IntcodeError::Overflow {
+            ip: self.ip,
+            op_code: self.load_instr(self.ip).unwrap_or(0),
+            bp: self.bp,
+            value,
+        }
+    }
+    fn invalid_addr(&self, addr: i64) -> IntcodeError {
+        IntcodeError::InvalidAddr {
+            ip: self.ip,
+            op_code: self.load_instr(self.ip).unwrap_or(0),
+            addr,
+            bp: self.bp,
+        }
+    }
+    fn io_error(&self, ip: i64, error: Error) -> IntcodeError {
+        IntcodeError::Io {
+            ip,
+            op_code: self.load_instr(ip).unwrap_or(0),
+            bp: self.bp,
+            error,
+        }
+    }
+}
+
+/// Drops the cached instructions which contain the cell at `addr`.
+fn evict(cache: &mut DecodeCache, addr: i64) {
+    let covers = |ip: usize| match cache.get(ip) {
+        Some((size, _)) => ip as i64 + size > addr,
+        None => false,
+    };
+    // Instructions are at most four cells long.
+    let lo = (addr - 3).max(0) as usize;
+    let stale = (lo..=addr as usize)
+        .filter(|&ip| covers(ip))
+        .collect::<Vec<_>>();
+    if !stale.is_empty() {
+        let cache = Arc::make_mut(cache);
+        for ip in stale {
+            cache.remove(ip);
+        }
+    }
+}
+
+/// A fault of the Intcode machine.
+///
+/// All variants record the `ip` and the relative base at the time of the
+/// fault, and, where it could be fetched, the raw opcode (modes included).
+#[derive(Debug)]
+pub enum IntcodeError {
+    /// `ip` points outside of memory.
+    InvalidIp {
+        ip: i64,
+        bp: i64,
+    },
+    InvalidOpCode {
+        ip: i64,
+        op_code: i64,
+        bp: i64,
+    },
+    /// A parameter mode other than 0, 1 or 2.
+    InvalidMode {
+        ip: i64,
+        op_code: i64,
+        mode: i64,
+        bp: i64,
+    },
+    /// Modes given for parameters the instruction doesn't have.
+    LeftoverModes {
+        ip: i64,
+        op_code: i64,
+        modes: i64,
+        bp: i64,
+    },
+    /// An immediate parameter in a position the instruction writes to.
+    ImmediateDst {
+        ip: i64,
+        op_code: i64,
+        bp: i64,
+    },
+    InvalidAddr {
+        ip: i64,
+        op_code: i64,
+        addr: i64,
+        bp: i64,
+    },
+    /// An arithmetic result, widened value or relative base out of the `i64`
+    /// range, see [`Overflow`]. Results past the `i128` range saturate.
+    Overflow {
+        ip: i64,
+        op_code: i64,
+        bp: i64,
+        value: i128,
+    },
+    /// [`IntCode::provide_input`] called again before the machine read the
+    /// previous value.
+    InputPending {
+        ip: i64,
+        bp: i64,
+        value: i64,
+    },
+    /// The `Io` failed to read or write a value.
+    Io {
+        ip: i64,
+        op_code: i64,
+        bp: i64,
+        error: Error,
+    },
+}
+
+impl IntcodeError {
+    pub fn ip(&self) -> i64 {
+        match *self {
+            IntcodeError::InvalidIp { ip, .. }
+            | IntcodeError::InvalidOpCode { ip, .. }
+            | IntcodeError::InvalidMode { ip, .. }
+            | IntcodeError::LeftoverModes { ip, .. }
+            | IntcodeError::ImmediateDst { ip, .. }
+            | IntcodeError::InvalidAddr { ip, .. }
+            | IntcodeError::Overflow { ip, .. }
+            | IntcodeError::InputPending { ip, .. }
+            | IntcodeError::Io { ip, .. } => ip,
+        }
+    }
+
+    pub fn bp(&self) -> i64 {
+        match *self {
+            IntcodeError::InvalidIp { bp, .. }
+            | IntcodeError::InvalidOpCode { bp, .. }
+            | IntcodeError::InvalidMode { bp, .. }
+            | IntcodeError::LeftoverModes { bp, .. }
+            | IntcodeError::ImmediateDst { bp, .. }
+            | IntcodeError::InvalidAddr { bp, .. }
+            | IntcodeError::Overflow { bp, .. }
+            | IntcodeError::InputPending { bp, .. }
+            | IntcodeError::Io { bp, .. } => bp,
+        }
+    }
+
+    pub fn op_code(&self) -> Option<i64> {
+        match *self {
+            IntcodeError::InvalidIp { .. } | IntcodeError::InputPending { .. } => None,
+            IntcodeError::InvalidOpCode { op_code, .. }
+            | IntcodeError::InvalidMode { op_code, .. }
+            | IntcodeError::LeftoverModes { op_code, .. }
+            | IntcodeError::ImmediateDst { op_code, .. }
+            | IntcodeError::InvalidAddr { op_code, .. }
+            | IntcodeError::Overflow { op_code, .. }
+            | IntcodeError::Io { op_code, .. } => Some(op_code),
+        }
+    }
+}
+
+impl std::error::Error for IntcodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IntcodeError::Io { error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntcodeError::InvalidIp { .. } => write!(f, "ip outside of memory")?,
+            IntcodeError::InvalidOpCode { op_code, .. } => {
+                write!(f, "invalid op code: {}", op_code)?
+            }
+            IntcodeError::InvalidMode { op_code, mode, .. } => {
+                write!(f, "invalid addr mode {} in {}", mode, op_code)?
+            }
+            IntcodeError::LeftoverModes { op_code, modes, .. } => {
+                write!(f, "leftover modes {} in {}", modes, op_code)?
+            }
+            IntcodeError::ImmediateDst { op_code, .. } => {
+                write!(f, "immediate destination in {}", op_code)?
+            }
+            IntcodeError::InvalidAddr { addr, .. } => write!(f, "invalid addr: {}", addr)?,
+            IntcodeError::Overflow { value, .. } => write!(f, "arithmetic overflow: {}", value)?,
+            IntcodeError::InputPending { value, .. } => {
+                write!(f, "input {} provided before the last one was read", value)?
+            }
+            IntcodeError::Io { error, .. } => write!(f, "io error: {}", error)?,
+        }
+        write!(f, " (ip = {}, rb = {})", self.ip(), self.bp())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Addr {
+    pub value: i64,
+    pub rel: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Immediate(i64),
+    Addr(Addr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Halt,
+    Arith {
+        op: ArithOp,
+        lhs: Value,
+        rhs: Value,
+        dst: Addr,
+    },
+    Input {
+        dst: Addr,
+    },
+    Output {
+        src: Value,
+    },
+    Jump {
+        op: JumpOp,
+        cond: Value,
+        tgt: Value,
+    },
+    AdjBp {
+        adj: Value,
+    },
+}
+
+impl Op {
+    /// Decodes the instruction at `ip`, returning its size in cells.
+    ///
+    /// `load` fetches raw memory cells, so this works both for a running
+    /// machine and for a static memory image. `bp` is only used for error
+    /// reporting.
+    pub fn decode(
+        ip: i64,
+        bp: i64,
+        load: impl Fn(i64) -> Option<i64>,
+    ) -> Result<(i64, Op), IntcodeError> {
+        fn to_value(modes: &mut i64, value: i64) -> Result<Value, i64> {
             let res = match *modes % 10 {
                 0 => Value::Addr(Addr { value, rel: false }),
                 1 => Value::Immediate(value),
                 2 => Value::Addr(Addr { value, rel: true }),
-                _ => Err("invalid addr mode")?,
+                mode => return Err(mode),
             };
             *modes /= 10;
             Ok(res)
         }
 
-        let op_code = self.load_instr(self.ip)?;
-        let (mut modes, op_code) = (op_code / 100, op_code % 100);
+        let raw = load(ip).ok_or(IntcodeError::InvalidIp { ip, bp })?;
+        let (mut modes, op_code) = (raw / 100, raw % 100);
         let mut size = 0;
         macro_rules! args {
             ($($m:ident)*) => {{
                 let res = ($(args!(@ $m),)*);
                 if modes != 0 {
-                    Err(format!("leftover modes: {}", modes))?
+                    Err(IntcodeError::LeftoverModes { ip, op_code: raw, modes, bp })?
                 }
                 size += 1;
                 res
             }};
             (@ v) => {{
                 size += 1;
-                let val = self.load_instr(self.ip + size)?;
-                to_value(&mut modes, val)?
+                // Instructions running past the end of the address space.
+                let addr = ip.checked_add(size).ok_or(IntcodeError::InvalidIp { ip, bp })?;
+                let val = load(addr).ok_or(IntcodeError::InvalidAddr { ip, op_code: raw, addr, bp })?;
+                to_value(&mut modes, val)
+                    .map_err(|mode| IntcodeError::InvalidMode { ip, op_code: raw, mode, bp })?
             }};
             (@ a) => {{
-                size += 1;
-                let val = self.load_instr(self.ip + size)?;
-                match to_value(&mut modes, val)? {
+                match args!(@ v) {
                     Value::Addr(it) => it,
-                    Value::Immediate(_) => Err("Immediate address")?,
+                    Value::Immediate(_) => Err(IntcodeError::ImmediateDst { ip, op_code: raw, bp })?,
                 }
             }};
         }
@@ -454,157 +1386,155 @@ impl<'a, IO: Io> IntCode<'a, IO> {
                 let (adj,) = args!(v);
                 Op::AdjBp { adj }
             }
-            99 => Op::Halt,
-            _ => Err(format!("invalid op code: {}", op_code))?,
+            99 => {
+                let () = args!();
+                Op::Halt
+            }
+            _ => Err(IntcodeError::InvalidOpCode {
+                ip,
+                op_code: raw,
+                bp,
+            })?,
         };
         Ok((size, res))
     }
-}
-
-#[derive(Debug, Clone, Copy)]
-struct Addr {
-    value: i64,
-    rel: bool,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum Value {
-    Immediate(i64),
-    Addr(Addr),
-}
-
-#[derive(Debug, Clone, Copy)]
-enum Op {
-    Halt,
-    Arith {
-        op: ArithOp,
-        lhs: Value,
-        rhs: Value,
-        dst: Addr,
-    },
-    Input {
-        dst: Addr,
-    },
-    Output {
-        src: Value,
-    },
-    Jump {
-        op: JumpOp,
-        cond: Value,
-        tgt: Value,
-    },
-    AdjBp {
-        adj: Value,
-    },
-}
 
-#[derive(Debug, Clone, Copy)]
-enum ArithOp {
-    Add,
-    Mul,
-    LessThan,
-    Equals,
-}
+    /// Inverse of [`Op::decode`].
+    pub fn encode(&self) -> Vec<i64> {
+        let op_code = self.op_code();
+        let args = match *self {
+            Op::Halt => vec![],
+            Op::Arith { lhs, rhs, dst, .. } => vec![lhs, rhs, Value::Addr(dst)],
+            Op::Input { dst } => vec![Value::Addr(dst)],
+            Op::Output { src } => vec![src],
+            Op::Jump { cond, tgt, .. } => vec![cond, tgt],
+            Op::AdjBp { adj } => vec![adj],
+        };
+        let modes = args.iter().rev().fold(0, |acc, arg| {
+            let mode = match arg {
+                Value::Addr(Addr { rel: false, .. }) => 0,
+                Value::Immediate(_) => 1,
+                Value::Addr(Addr { rel: true, .. }) => 2,
+            };
+            acc * 10 + mode
+        });
+        let mut res = vec![modes * 100 + op_code];
+        res.extend(args.iter().map(|arg| match *arg {
+            Value::Immediate(it) => it,
+            Value::Addr(addr) => addr.value,
+        }));
+        res
+    }
 
-impl ArithOp {
-    fn eval(&self, lhs: i64, rhs: i64) -> i64 {
+    /// The op code, without parameter modes.
+    pub fn op_code(&self) -> i64 {
         match self {
-            ArithOp::Add => lhs + rhs,
-            ArithOp::Mul => lhs * rhs,
-            ArithOp::LessThan => (lhs < rhs) as i64,
-            ArithOp::Equals => (lhs == rhs) as i64,
+            Op::Halt => 99,
+            Op::Arith { op, .. } => match op {
+                ArithOp::Add => 1,
+                ArithOp::Mul => 2,
+                ArithOp::LessThan => 7,
+                ArithOp::Equals => 8,
+            },
+            Op::Input { .. } => 3,
+            Op::Output { .. } => 4,
+            Op::Jump { op, .. } => match op {
+                JumpOp::IfTrue => 5,
+                JumpOp::IfFalse => 6,
+            },
+            Op::AdjBp { .. } => 9,
         }
     }
-}
-
-#[derive(Debug, Clone, Copy)]
-enum JumpOp {
-    IfTrue,
-    IfFalse,
-}
 
-pub fn extend_memory(mem: &mut Vec<i64>) {
-    let limit = 64 * 1024;
-    if mem.len() < limit {
-        mem.resize(limit, 0);
+    /// Number of cells the encoded instruction occupies.
+    pub fn size(&self) -> i64 {
+        match self {
+            Op::Halt => 1,
+            Op::Input { .. } | Op::Output { .. } | Op::AdjBp { .. } => 2,
+            Op::Jump { .. } => 3,
+            Op::Arith { .. } => 4,
+        }
     }
-}
-
-pub struct StepIo {
-    read_slot: Option<i64>,
-    write_slot: Option<i64>,
-}
 
-impl Default for StepIo {
-    fn default() -> Self {
-        StepIo {
-            read_slot: None,
-            write_slot: Some(0),
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Op::Halt => "hlt",
+            Op::Arith { op, .. } => match op {
+                ArithOp::Add => "add",
+                ArithOp::Mul => "mul",
+                ArithOp::LessThan => "lt",
+                ArithOp::Equals => "eq",
+            },
+            Op::Input { .. } => "in",
+            Op::Output { .. } => "out",
+            Op::Jump { op, .. } => match op {
+                JumpOp::IfTrue => "jt",
+                JumpOp::IfFalse => "jf",
+            },
+            Op::AdjBp { .. } => "arb",
         }
     }
 }
 
-#[derive(Debug)]
-struct ReadFail;
-impl std::error::Error for ReadFail {}
-impl fmt::Display for ReadFail {
+impl fmt::Display for Addr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ReadFail")
+        match (self.rel, self.value) {
+            (false, value) => write!(f, "[{}]", value),
+            (true, value) if value < 0 => write!(f, "[rb{}]", value),
+            (true, value) => write!(f, "[rb+{}]", value),
+        }
     }
 }
 
-#[derive(Debug)]
-struct WriteFail;
-impl std::error::Error for WriteFail {}
-impl fmt::Display for WriteFail {
+impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "WriteFail")
+        match self {
+            Value::Immediate(it) => write!(f, "#{}", it),
+            Value::Addr(it) => write!(f, "{}", it),
+        }
     }
 }
 
-impl Io for StepIo {
-    fn read(&mut self) -> Result<i64> {
-        let res = self.read_slot.take().ok_or(ReadFail)?;
-        Ok(res)
-    }
-    fn write(&mut self, value: i64) -> Result<()> {
-        if self.write_slot.is_some() {
-            Err(WriteFail)?;
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let m = self.mnemonic();
+        match self {
+            Op::Halt => write!(f, "{}", m),
+            Op::Arith { lhs, rhs, dst, .. } => write!(f, "{} {}, {} -> {}", m, lhs, rhs, dst),
+            Op::Input { dst } => write!(f, "{} -> {}", m, dst),
+            Op::Output { src } => write!(f, "{} {}", m, src),
+            Op::Jump { cond, tgt, .. } => write!(f, "{} {}, {}", m, cond, tgt),
+            Op::AdjBp { adj } => write!(f, "{} {}", m, adj),
         }
-        self.write_slot = Some(value);
-        Ok(())
     }
 }
 
-impl<'a> IntCode<'a, StepIo> {
-    pub fn new_step(mem: &'a mut [i64]) -> Self {
-        IntCode::new(StepIo::default(), mem)
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ArithOp {
+    Add,
+    Mul,
+    LessThan,
+    Equals,
+}
 
-    pub fn input(&mut self, value: i64) {
-        loop {
-            match self.step() {
-                Ok(_) => (),
-                Err(e) if e.downcast_ref::<ReadFail>().is_some() => break,
-                Err(_) => panic!(),
-            }
+impl ArithOp {
+    /// Computes the exact result, which may not fit in an `i64`. Fails with
+    /// the saturated result if it doesn't fit in an `i128` either, which
+    /// takes widened operands.
+    fn eval(&self, lhs: i128, rhs: i128) -> Result<i128, i128> {
+        match self {
+            ArithOp::Add => lhs.checked_add(rhs).ok_or(lhs.saturating_add(rhs)),
+            ArithOp::Mul => lhs.checked_mul(rhs).ok_or(lhs.saturating_mul(rhs)),
+            ArithOp::LessThan => Ok((lhs < rhs) as i128),
+            ArithOp::Equals => Ok((lhs == rhs) as i128),
         }
-        self.io.read_slot = Some(value);
-        self.step().unwrap();
     }
+}
 
-    pub fn output(&mut self) -> i64 {
-        loop {
-            match self.step() {
-                Ok(_) => (),
-                Err(e) if e.downcast_ref::<WriteFail>().is_some() => break,
-                Err(_) => panic!(),
-            }
-        }
-        self.io.write_slot = None;
-        self.step().unwrap();
-        self.io.write_slot.unwrap()
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum JumpOp {
+    IfTrue,
+    IfFalse,
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -736,3 +1666,387 @@ impl<T> ops::IndexMut<Point> for Board<T> {
         self.get_mut(index).unwrap()
     }
 }
+
+#[test]
+fn test_paged_memory() {
+    let image = (0..3000).collect::<Vec<i64>>();
+    let mut mem = PagedMemory::new(&image);
+    *mem.cell_mut(5).unwrap() = -1;
+    *mem.cell_mut(100_000).unwrap() = 92;
+
+    let mut fork = mem.clone();
+    *fork.cell_mut(2500).unwrap() = -2;
+    assert_eq!(mem.cell(2500), Some(2500));
+    assert_eq!(fork.cell(2500), Some(-2));
+    assert_eq!(fork.cell(5), Some(-1));
+    assert_eq!(fork.cell(100_000), Some(92));
+    assert_eq!(fork.cell(100_001), Some(0));
+    assert_eq!(fork.cell(MEM_LIMIT), None);
+
+    fork.reset();
+    for addr in [5, 2500, 100_000].iter().copied() {
+        assert_eq!(fork.cell(addr), Some(image.get(addr).copied().unwrap_or(0)));
+    }
+    assert_eq!(mem.cell(5), Some(-1));
+
+    // A stray write past the limit of a `Vec` faults instead of allocating.
+    let prog = vec![1101, 1, 1, 1 << 29, 99];
+    let mut cpu = IntCode::new(MemIo::new(vec![]), prog.clone());
+    match cpu.run() {
+        Err(IntcodeError::InvalidAddr { ip: 0, addr, .. }) => assert_eq!(addr, 1 << 29),
+        res => panic!("unexpected {:?}", res),
+    }
+    assert_eq!(cpu.mem().len(), 5);
+    let mut cpu = IntCode::new(MemIo::new(vec![]), PagedMemory::new(&prog));
+    cpu.run().unwrap();
+    assert_eq!(cpu.mem().cell(1 << 29), Some(2));
+
+    // Code far away only takes a chunk of the cache.
+    let far = 1 << 29;
+    let mut mem = PagedMemory::new(&[1106, 0, far]);
+    for (i, &it) in [104, 7, 99].iter().enumerate() {
+        *mem.cell_mut(far as usize + i).unwrap() = it;
+    }
+    let mut cpu = IntCode::new(MemIo::new(vec![]), mem).with_cache();
+    cpu.run().unwrap();
+    assert_eq!(cpu.io.output(), &[7]);
+    assert_eq!(cpu.cache.as_ref().unwrap().0.iter().flatten().count(), 2);
+}
+
+#[test]
+fn test_overflow() {
+    let prog = vec![1002, 7, 3, 7, 4, 7, 99, i64::MAX / 2];
+    let mut cpu = IntCode::new(MemIo::new(vec![]), prog.clone());
+    assert_eq!(cpu.overflow(), Overflow::Checked);
+    match cpu.run() {
+        Err(IntcodeError::Overflow { ip: 0, value, .. }) => {
+            assert_eq!(value, i64::MAX as i128 / 2 * 3)
+        }
+        res => panic!("unexpected {:?}", res),
+    }
+    assert_eq!(cpu.mem()[7], i64::MAX / 2);
+
+    let mut cpu = IntCode::new(MemIo::new(vec![]), prog.clone()).with_overflow(Overflow::Wrapping);
+    cpu.run().unwrap();
+    assert_eq!(cpu.io.into_output(), vec![(i64::MAX / 2).wrapping_mul(3)]);
+
+    let mut cpu = IntCode::new(MemIo::new(vec![]), vec![1101, i64::MIN, -1, 0, 99]);
+    assert_eq!(
+        cpu.run().unwrap_err().to_string(),
+        format!(
+            "arithmetic overflow: {} (ip = 0, rb = 0)",
+            i64::MIN as i128 - 1
+        )
+    );
+
+    // Widened results can be used by arithmetic, but not output.
+    let exact = i64::MAX as i128 / 2 * 3;
+    let mut cpu = IntCode::new(MemIo::new(vec![]), prog).with_overflow(Overflow::Widened);
+    match cpu.run() {
+        Err(IntcodeError::Overflow { ip: 4, value, .. }) => assert_eq!(value, exact),
+        res => panic!("unexpected {:?}", res),
+    }
+    assert_eq!(cpu.mem()[7], exact as i64);
+    assert_eq!(cpu.exact(7), Some(exact));
+    let prog = vec![
+        1002,
+        11,
+        3,
+        11,
+        1001,
+        11,
+        -i64::MAX,
+        11,
+        4,
+        11,
+        99,
+        i64::MAX / 2,
+    ];
+    let mut cpu = IntCode::new(MemIo::new(vec![]), prog).with_overflow(Overflow::Widened);
+    cpu.run().unwrap();
+    assert_eq!(cpu.io.output(), &[(exact - i64::MAX as i128) as i64]);
+    assert_eq!(cpu.exact(11), Some(exact - i64::MAX as i128));
+
+    // So does the relative base.
+    let prog = vec![109, i64::MAX, 109, i64::MAX, 99];
+    let mut cpu = IntCode::new(MemIo::new(vec![]), prog.clone());
+    match cpu.run() {
+        Err(IntcodeError::Overflow { ip: 2, value, .. }) => {
+            assert_eq!(value, i64::MAX as i128 * 2)
+        }
+        res => panic!("unexpected {:?}", res),
+    }
+    let mut cpu = IntCode::new(MemIo::new(vec![]), prog).with_overflow(Overflow::Wrapping);
+    cpu.run().unwrap();
+    assert_eq!(cpu.bp(), -2);
+    let mut cpu = IntCode::new(MemIo::new(vec![]), vec![109, i64::MAX, 204, 1, 99]);
+    assert!(matches!(
+        cpu.run(),
+        Err(IntcodeError::Overflow { ip: 2, .. })
+    ));
+}
+
+#[test]
+fn test_budget() {
+    // Counts down from 3, with an `in` at the end.
+    let prog = crate::asm::assemble(
+        "
+        loop:   out [n]
+                add [n], #-1 -> [n]
+                jt [n], #loop
+                in -> [n]
+                hlt
+        n:      .data 3
+        ",
+    )
+    .unwrap();
+    let mut cpu = IntCode::new(MemIo::new(vec![7]), prog.clone());
+    assert_eq!(cpu.run_for(4).unwrap(), Status::BudgetExhausted);
+    assert_eq!(cpu.io.output(), &[3, 2]);
+    assert_eq!(cpu.ip(), 2);
+    assert_eq!(cpu.run_for(0).unwrap(), Status::BudgetExhausted);
+    assert_eq!(cpu.run_for(100).unwrap(), Status::Halted);
+    assert_eq!(cpu.io.output(), &[3, 2, 1]);
+    assert_eq!(cpu.mem()[cpu.mem().len() - 1], 7);
+
+    // Outputs are expensive, and the budget is refilled between runs.
+    let costs = Costs::uniform(1).set(4, 10).unwrap();
+    let mut budget = Budget::new(12).with_costs(costs);
+    let mut cpu = IntCode::new(MemIo::new(vec![7]), prog.clone());
+    assert_eq!(
+        cpu.run_with_budget(&mut budget).unwrap(),
+        Status::BudgetExhausted
+    );
+    assert_eq!((cpu.io.output(), budget.gas()), (&[3][..], 0));
+    budget.refill(11);
+    assert_eq!(
+        cpu.run_with_budget(&mut budget).unwrap(),
+        Status::BudgetExhausted
+    );
+    assert_eq!((cpu.io.output(), budget.gas()), (&[3, 2][..], 0));
+    budget.refill(100);
+    assert_eq!(cpu.run_with_budget(&mut budget).unwrap(), Status::Halted);
+    assert_eq!(budget.gas(), 100 - 10 - 4);
+    budget.refill(u64::MAX);
+    assert_eq!(budget.gas(), u64::MAX);
+
+    // Halting is free, whatever its cost is set to.
+    let costs = Costs::uniform(5).set(99, 5).unwrap();
+    assert_eq!((costs.get(1), costs.get(99)), (Some(5), Some(0)));
+    assert_eq!(costs.get(42), None);
+    let err = costs.set(42, 1).unwrap_err();
+    assert_eq!(err.to_string(), "invalid op code: 42");
+
+    let flag = Arc::new(AtomicBool::new(true));
+    let mut budget = Budget::new(100).with_cancel(flag.clone());
+    let mut cpu = IntCode::new(MemIo::new(vec![7]), prog);
+    assert_eq!(cpu.run_with_budget(&mut budget).unwrap(), Status::Cancelled);
+    assert_eq!(cpu.ip(), 0);
+    flag.store(false, Ordering::Relaxed);
+    assert_eq!(cpu.run_with_budget(&mut budget).unwrap(), Status::Halted);
+}
+
+#[test]
+fn test_cache() {
+    use crate::asm::assemble;
+
+    fn run(mut cpu: IntCode<MemIo>) -> Vec<i64> {
+        cpu.run().unwrap();
+        cpu.io.into_output()
+    }
+
+    // Patches the operands of `out` and of the decrement.
+    let prog = assemble(
+        "
+        loop:   out #10
+                add [loop+1], #1 -> [loop+1]
+        dec:    add [n], #-1 -> [n]
+                add #-2, #0 -> [dec+2]
+                jt [n], #loop
+                hlt
+        n:      .data 5
+        ",
+    )
+    .unwrap();
+    let plain = run(IntCode::new(MemIo::new(vec![]), prog.clone()));
+    assert_eq!(plain, vec![10, 11, 12]);
+    assert_eq!(
+        run(IntCode::new(MemIo::new(vec![]), prog.clone()).with_cache()),
+        plain
+    );
+
+    let mut proto = IntCode::new(MemIo::new(vec![]), PagedMemory::new(&prog));
+    proto.predecode(0..prog.len() as i64);
+    for _ in 0..2 {
+        let mut cpu = proto.clone();
+        cpu.io = MemIo::new(vec![]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.io.into_output(), plain);
+    }
+
+    // Poking memory directly drops the cache.
+    let mut cpu = IntCode::new(MemIo::new(vec![]), vec![104, 1, 99]).with_cache();
+    assert!(cpu.step().unwrap());
+    cpu.set_ip(0);
+    cpu.mem_mut()[1] = 2;
+    cpu.run().unwrap();
+    assert_eq!(cpu.io.into_output(), vec![1, 2]);
+}
+
+#[test]
+fn test_errors() {
+    fn run(prog: Vec<i64>) -> IntcodeError {
+        IntCode::new(MemIo::new(vec![]), prog).run().unwrap_err()
+    }
+
+    match run(vec![109, 3, 1101, 1, 1, 0, 42]) {
+        IntcodeError::InvalidOpCode {
+            ip: 6,
+            op_code: 42,
+            bp: 3,
+        } => (),
+        err => panic!("{:?}", err),
+    }
+    match run(vec![1301, 0, 0, 0, 99]) {
+        IntcodeError::InvalidMode {
+            ip: 0,
+            op_code: 1301,
+            mode: 3,
+            ..
+        } => (),
+        err => panic!("{:?}", err),
+    }
+    match run(vec![10004, 0, 99]) {
+        IntcodeError::LeftoverModes { modes: 10, .. } => (),
+        err => panic!("{:?}", err),
+    }
+    match run(vec![11101, 0, 0, 0, 99]) {
+        IntcodeError::ImmediateDst { op_code: 11101, .. } => (),
+        err => panic!("{:?}", err),
+    }
+    match run(vec![109, -5, 204, 1, 99]) {
+        IntcodeError::InvalidAddr {
+            ip: 2, addr: -4, ..
+        } => (),
+        err => panic!("{:?}", err),
+    }
+    let err = run(vec![1105, 1, -1]);
+    assert_eq!(err.to_string(), "ip outside of memory (ip = -1, rb = 0)");
+    let io = tape::Replay::new(vec![tape::TapeEvent::Output(1)]);
+    let err = IntCode::new(io, vec![3, 0, 99]).run().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "io error: divergence at step 0: expected output 1, got input (ip = 0, rb = 0)"
+    );
+    assert_eq!(err.op_code(), Some(3));
+}
+
+#[test]
+fn test_awaiting_input() {
+    // Outputs the sum of its inputs until it reads 0.
+    let prog = vec![3, 13, 1006, 13, 12, 1, 13, 14, 14, 1105, 1, 0, 99, 0, 0];
+    let mut cpu = IntCode::new(MemIo::new(vec![1, 2]), prog);
+    assert_eq!(cpu.run().unwrap(), Status::AwaitingInput);
+    assert_eq!(cpu.ip(), 0);
+    assert_eq!(cpu.run().unwrap(), Status::AwaitingInput);
+    assert_eq!(cpu.mem()[14], 3);
+
+    // The read is retried once there is more input.
+    cpu.io = MemIo::new(vec![4, 0]);
+    assert_eq!(cpu.run().unwrap(), Status::Halted);
+    assert_eq!(cpu.mem()[14], 7);
+
+    let mut cpu = IntCode::new(MemIo::new(vec![]), vec![3, 0, 99]);
+    assert!(!cpu.step().unwrap());
+    assert_eq!(cpu.run_for(10).unwrap(), Status::AwaitingInput);
+}
+
+#[test]
+fn test_ascii_script() {
+    // Echoes its input, prompting with `?` for every line.
+    let prog = asm::assemble(
+        "
+        start:  out #63
+        loop:   in -> [c]
+                out [c]
+                eq [c], #10 -> [t]
+                jt [t], #start
+                jt #1, #loop
+        c:      .data 0
+        t:      .data 0
+        ",
+    )
+    .unwrap();
+    let script = "# answers ab with yes\n!when ab => yes\nab\ncd\n";
+    let io = AsciiIo::script(script).with_output(Vec::new());
+    let mut cpu = IntCode::new(io, prog.clone());
+    assert_eq!(cpu.run().unwrap(), Status::AwaitingInput);
+    let out = String::from_utf8(cpu.io.into_output()).unwrap();
+    assert_eq!(out, "?ab\n?yes\n?cd\n?");
+
+    // Rules only see the output since the last line sent.
+    let io = AsciiIo::script("x")
+        .with_rule("?x", "never")
+        .with_echo(true)
+        .with_output(Vec::new());
+    let mut cpu = IntCode::new(io, prog);
+    assert_eq!(cpu.run().unwrap(), Status::AwaitingInput);
+    let out = String::from_utf8(cpu.io.into_output()).unwrap();
+    assert_eq!(out, "?x\nx\n?");
+}
+
+#[test]
+fn test_events() {
+    // Outputs twice its input.
+    let mut cpu = IntCode::new((), vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0]);
+    assert_eq!(cpu.run_until_event().unwrap(), Event::NeedsInput);
+    // Blocked machines stay blocked.
+    assert_eq!(cpu.run_until_event().unwrap(), Event::NeedsInput);
+    assert_eq!(cpu.ip(), 0);
+    cpu.provide_input(21).unwrap();
+    let err = cpu.provide_input(5).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "input 5 provided before the last one was read (ip = 0, rb = 0)"
+    );
+    assert_eq!(cpu.run_until_event().unwrap(), Event::Output(42));
+    assert_eq!(cpu.run_until_event().unwrap(), Event::Halted);
+    assert_eq!(cpu.run_until_event().unwrap(), Event::Halted);
+    assert_eq!(cpu.ip(), 8);
+}
+
+#[test]
+fn test_fork() {
+    // Outputs the running sum of its inputs.
+    let prog = asm::assemble(
+        "
+        loop:   in -> [x]
+                add [acc], [x] -> [acc]
+                out [acc]
+                jt #1, #loop
+        x:      .data 0
+        acc:    .data 0
+        ",
+    )
+    .unwrap();
+    let feed = |cpu: &mut IntCode<()>, value| {
+        assert_eq!(cpu.run_until_event().unwrap(), Event::NeedsInput);
+        cpu.provide_input(value).unwrap();
+        cpu.run_until_event().unwrap()
+    };
+    let mut cpu = IntCode::new((), prog.clone());
+    assert_eq!(feed(&mut cpu, 10), Event::Output(10));
+    let mut fork = cpu.clone();
+    assert_eq!(feed(&mut cpu, 1), Event::Output(11));
+    assert_eq!(feed(&mut fork, 5), Event::Output(15));
+    assert_eq!(feed(&mut cpu, 1), Event::Output(12));
+    assert_eq!(fork.mem()[12], 15);
+
+    // Borrowed memory is modified in place.
+    let mut mem = prog;
+    let mut cpu = IntCode::new(MemIo::new(vec![3, 4]), &mut mem);
+    assert_eq!(cpu.run().unwrap(), Status::AwaitingInput);
+    assert_eq!(cpu.io.output(), &[3, 7]);
+    drop(cpu);
+    assert_eq!(mem[12], 7);
+}
@@ -0,0 +1,428 @@
+//! Simulation of a packet network of Intcode NICs, as in day23.
+//!
+//! Every node runs the same program. It first reads its address, then sends
+//! packets by outputting a destination followed by a fixed number of payload
+//! values, and receives them as payload values, reading a placeholder (`-1`)
+//! when its queue is empty. A [`Router`] decides where packets go, and a
+//! [`Nat`] gets to send a packet whenever the network goes idle.
+//!
+//! Scheduling is round-robin: every [`Network::round`] runs each node in
+//! address order until it reads from an empty queue, or runs out of its
+//! instruction budget, so simulations are deterministic.
+use std::{collections::VecDeque, error, fmt};
+
+use crate::{Budget, IntCode, IntcodeError, Io, Result, Status};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Packet {
+    pub dst: i64,
+    pub payload: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Route {
+    Node(usize),
+    Nat,
+    Drop,
+}
+
+pub trait Router {
+    /// Packets routed to a node outside of `0..n_nodes` are dropped.
+    fn route(&mut self, packet: &Packet, n_nodes: usize) -> Route;
+}
+
+/// Routes packets to the node with their destination address, or to the NAT
+/// at address `nat`. Others are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddrRouter {
+    pub nat: i64,
+}
+
+impl Default for AddrRouter {
+    fn default() -> AddrRouter {
+        AddrRouter { nat: 255 }
+    }
+}
+
+impl Router for AddrRouter {
+    fn route(&mut self, packet: &Packet, n_nodes: usize) -> Route {
+        match packet.dst {
+            dst if dst == self.nat => Route::Nat,
+            dst if 0 <= dst && (dst as usize) < n_nodes => Route::Node(dst as usize),
+            _ => Route::Drop,
+        }
+    }
+}
+
+pub trait Nat {
+    fn receive(&mut self, packet: Packet);
+    /// Called when the network is idle, returns the packet to send if any.
+    fn wake(&mut self) -> Option<Packet>;
+}
+
+/// No NAT, packets routed to it are dropped.
+impl Nat for () {
+    fn receive(&mut self, _packet: Packet) {}
+    fn wake(&mut self) -> Option<Packet> {
+        None
+    }
+}
+
+/// Remembers the last packet received, and sends it to node 0 whenever the
+/// network is idle.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LastPacketNat {
+    last: Option<Packet>,
+    sent: Vec<Packet>,
+}
+
+impl LastPacketNat {
+    pub fn new() -> LastPacketNat {
+        LastPacketNat::default()
+    }
+    pub fn last(&self) -> Option<&Packet> {
+        self.last.as_ref()
+    }
+    /// The packets sent so far, oldest first.
+    pub fn sent(&self) -> &[Packet] {
+        &self.sent
+    }
+}
+
+impl Nat for LastPacketNat {
+    fn receive(&mut self, packet: Packet) {
+        self.last = Some(packet)
+    }
+    fn wake(&mut self) -> Option<Packet> {
+        let packet = Packet {
+            dst: 0,
+            payload: self.last.as_ref()?.payload.clone(),
+        };
+        self.sent.push(packet.clone());
+        Some(packet)
+    }
+}
+
+/// A packet sent through the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub round: u64,
+    /// The sending node, `None` for the NAT and packets from outside.
+    pub src: Option<usize>,
+    pub packet: Packet,
+    pub route: Route,
+}
+
+#[derive(Debug)]
+pub struct NetError {
+    pub node: usize,
+    pub error: IntcodeError,
+}
+
+impl error::Error for NetError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node {}: {}", self.node, self.error)
+    }
+}
+
+struct Nic {
+    cpu: IntCode<NicIo>,
+    halted: bool,
+}
+
+struct NicIo {
+    queue: VecDeque<i64>,
+    send_buf: Vec<i64>,
+}
+
+impl Io for NicIo {
+    fn read(&mut self) -> Result<Option<i64>> {
+        Ok(self.queue.pop_front())
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        self.send_buf.push(value);
+        Ok(())
+    }
+}
+
+pub struct Network<R = AddrRouter, N = LastPacketNat> {
+    nics: Vec<Nic>,
+    arity: usize,
+    empty: i64,
+    budget: u64,
+    busy: bool,
+    router: R,
+    nat: N,
+    log: Vec<LogEntry>,
+    rounds: u64,
+    idle: bool,
+}
+
+impl<R: Router, N: Nat> Network<R, N> {
+    /// Boots `n_nodes` nodes running `program`, with addresses from 0.
+    pub fn new(program: &[i64], n_nodes: usize, router: R, nat: N) -> Network<R, N> {
+        let nics = (0..n_nodes)
+            .map(|addr| {
+                let io = NicIo {
+                    queue: vec![addr as i64].into(),
+                    send_buf: Vec::new(),
+                };
+                Nic {
+                    cpu: IntCode::new(io, program.to_vec()),
+                    halted: false,
+                }
+            })
+            .collect();
+        Network {
+            nics,
+            arity: 2,
+            empty: -1,
+            budget: 1_000_000,
+            busy: false,
+            router,
+            nat,
+            log: Vec::new(),
+            rounds: 0,
+            idle: false,
+        }
+    }
+    /// Sets the number of payload values per packet, 2 by default.
+    pub fn with_arity(mut self, arity: usize) -> Network<R, N> {
+        self.arity = arity;
+        self
+    }
+    /// Sets the value read from an empty queue, -1 by default.
+    pub fn with_empty_read(mut self, value: i64) -> Network<R, N> {
+        self.empty = value;
+        self
+    }
+    /// Sets the number of instructions each node may run per round,
+    /// 1,000,000 by default. A node which runs out of it isn't idle.
+    pub fn with_budget(mut self, n_steps: u64) -> Network<R, N> {
+        self.budget = n_steps;
+        self
+    }
+
+    pub fn nat(&self) -> &N {
+        &self.nat
+    }
+    pub fn nat_mut(&mut self) -> &mut N {
+        &mut self.nat
+    }
+    pub fn log(&self) -> &[LogEntry] {
+        &self.log
+    }
+    pub fn rounds(&self) -> u64 {
+        self.rounds
+    }
+    /// Whether no node sent anything or ran out of budget in the last round,
+    /// and all queues are empty. Checked before waking the NAT.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+    pub fn is_halted(&self, node: usize) -> bool {
+        self.nics[node].halted
+    }
+
+    /// Sends a packet from outside the network, as the NAT would. Replaying
+    /// the packets of a log this way reproduces a simulation.
+    pub fn send(&mut self, packet: Packet) {
+        self.deliver(None, packet)
+    }
+
+    /// Runs every node until it reads from an empty queue, halts or runs out
+    /// of budget, then wakes the NAT if the network is idle.
+    pub fn round(&mut self) -> Result<(), NetError> {
+        let sent = self.log.len();
+        self.busy = false;
+        for idx in 0..self.nics.len() {
+            self.run_nic(idx)
+                .map_err(|error| NetError { node: idx, error })?;
+        }
+        self.idle = !self.busy
+            && self.log.len() == sent
+            && self.nics.iter().all(|it| it.cpu.io.queue.is_empty());
+        if self.idle {
+            if let Some(packet) = self.nat.wake() {
+                self.deliver(None, packet);
+            }
+        }
+        self.rounds += 1;
+        Ok(())
+    }
+    /// Runs rounds until the network goes idle, or for at most `max_rounds`.
+    /// Returns whether it went idle.
+    pub fn run_until_idle(&mut self, max_rounds: u64) -> Result<bool, NetError> {
+        for _ in 0..max_rounds {
+            self.round()?;
+            if self.idle {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn run_nic(&mut self, idx: usize) -> Result<(), IntcodeError> {
+        let mut budget = Budget::new(self.budget);
+        while !self.nics[idx].halted {
+            let status = self.nics[idx].cpu.run_with_budget(&mut budget)?;
+            self.send_from(idx);
+            let nic = &mut self.nics[idx];
+            match status {
+                // Packets the node sent to itself are read before the empty
+                // placeholder.
+                Status::AwaitingInput if !nic.cpu.io.queue.is_empty() => (),
+                Status::AwaitingInput => {
+                    nic.cpu.provide_input(self.empty)?;
+                    break;
+                }
+                Status::Halted => nic.halted = true,
+                Status::BudgetExhausted | Status::Cancelled => {
+                    self.busy = true;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Delivers the complete packets sent by node `idx`.
+    fn send_from(&mut self, idx: usize) {
+        let chunk = self.arity + 1;
+        let buf = &mut self.nics[idx].cpu.io.send_buf;
+        let packets = buf
+            .drain(..buf.len() / chunk * chunk)
+            .collect::<Vec<_>>()
+            .chunks(chunk)
+            .map(|it| Packet {
+                dst: it[0],
+                payload: it[1..].to_vec(),
+            })
+            .collect::<Vec<_>>();
+        for packet in packets {
+            self.deliver(Some(idx), packet);
+        }
+    }
+
+    fn deliver(&mut self, src: Option<usize>, packet: Packet) {
+        let route = match self.router.route(&packet, self.nics.len()) {
+            Route::Node(dst) if dst >= self.nics.len() => Route::Drop,
+            route => route,
+        };
+        self.log.push(LogEntry {
+            round: self.rounds,
+            src,
+            packet: packet.clone(),
+            route,
+        });
+        match route {
+            Route::Node(dst) => self.nics[dst].cpu.io.queue.extend(packet.payload),
+            Route::Nat => self.nat.receive(packet),
+            Route::Drop => (),
+        }
+    }
+}
+
+#[test]
+fn test_network() {
+    // Passes a counter around a ring of 3 nodes, and reports to the NAT
+    // when it reaches 5.
+    let prog = crate::lang::compile(
+        "
+        fn main() {
+            let addr = input();
+            if addr == 0 {
+                output(1);
+                output(1);
+                output(100);
+            }
+            while 1 {
+                let x = input();
+                if x != -1 {
+                    let y = input();
+                    if x < 5 {
+                        let next = addr + 1;
+                        if next == 3 {
+                            next = 0;
+                        }
+                        output(next);
+                        output(x + 1);
+                        output(y + addr);
+                    } else {
+                        output(255);
+                        output(x);
+                        output(y);
+                    }
+                }
+            }
+        }
+        ",
+    )
+    .unwrap();
+    let mut net = Network::new(&prog, 3, AddrRouter::default(), LastPacketNat::new());
+    assert!(net.run_until_idle(10).unwrap());
+    assert_eq!(net.rounds(), 3);
+    let packet = |dst, x, y| Packet {
+        dst,
+        payload: vec![x, y],
+    };
+    assert_eq!(net.nat().last(), Some(&packet(255, 5, 104)));
+    assert_eq!(net.nat().sent(), &[packet(0, 5, 104)]);
+
+    // The NAT resends its last packet on every idle round.
+    while net.nat().sent().len() < 3 {
+        net.round().unwrap();
+    }
+    assert_eq!(net.rounds(), 7);
+    let log = net
+        .log()
+        .iter()
+        .map(|it| (it.round, it.src, it.route, it.packet.payload[0]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        log,
+        vec![
+            (0, Some(0), Route::Node(1), 1),
+            (0, Some(1), Route::Node(2), 2),
+            (0, Some(2), Route::Node(0), 3),
+            (1, Some(0), Route::Node(1), 4),
+            (1, Some(1), Route::Node(2), 5),
+            (1, Some(2), Route::Nat, 5),
+            (2, None, Route::Node(0), 5),
+            (3, Some(0), Route::Nat, 5),
+            (4, None, Route::Node(0), 5),
+            (5, Some(0), Route::Nat, 5),
+            (6, None, Route::Node(0), 5),
+        ]
+    );
+
+    // Other arities, and dropped packets.
+    let prog = [3, 0, 104, 7, 104, 1, 104, 2, 104, 3, 99];
+    let mut net = Network::new(&prog, 1, AddrRouter::default(), ()).with_arity(3);
+    net.round().unwrap();
+    assert_eq!(net.log()[0].packet.payload, vec![1, 2, 3]);
+    assert_eq!(net.log()[0].route, Route::Drop);
+    assert!(net.is_halted(0));
+
+    // Routes to nodes out of range are dropped.
+    struct Far;
+    impl Router for Far {
+        fn route(&mut self, _packet: &Packet, n_nodes: usize) -> Route {
+            Route::Node(n_nodes)
+        }
+    }
+    let mut net = Network::new(&prog, 1, Far, ()).with_arity(3);
+    net.round().unwrap();
+    assert_eq!(net.log()[0].route, Route::Drop);
+
+    // Nodes which never read their queue can't stall a round, nor let the
+    // network go idle.
+    let mut net = Network::new(&[1105, 1, 0], 2, AddrRouter::default(), ()).with_budget(100);
+    assert!(!net.run_until_idle(3).unwrap());
+    assert_eq!(net.rounds(), 3);
+}
@@ -0,0 +1,207 @@
+//! Counting where an Intcode program spends its time.
+//!
+//! [`Profiler`] is a [`Trace`], so profiling is opt-in: run the machine with
+//! `run_traced(&mut profiler)` and ask for the [`Profile`] when it halts.
+use std::{collections::HashMap, fmt};
+
+use crate::{ArithOp, JumpOp, Op, Trace};
+
+/// Instructions are grouped by their opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OpKind {
+    Arith(ArithOp),
+    Jump(JumpOp),
+    Input,
+    Output,
+    AdjBp,
+    Halt,
+}
+
+impl OpKind {
+    pub fn of(op: &Op) -> OpKind {
+        match *op {
+            Op::Halt => OpKind::Halt,
+            Op::Arith { op, .. } => OpKind::Arith(op),
+            Op::Input { .. } => OpKind::Input,
+            Op::Output { .. } => OpKind::Output,
+            Op::Jump { op, .. } => OpKind::Jump(op),
+            Op::AdjBp { .. } => OpKind::AdjBp,
+        }
+    }
+}
+
+impl fmt::Display for OpKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OpKind::Arith(ArithOp::Add) => "add",
+            OpKind::Arith(ArithOp::Mul) => "mul",
+            OpKind::Arith(ArithOp::LessThan) => "lt",
+            OpKind::Arith(ArithOp::Equals) => "eq",
+            OpKind::Jump(JumpOp::IfTrue) => "jt",
+            OpKind::Jump(JumpOp::IfFalse) => "jf",
+            OpKind::Input => "in",
+            OpKind::Output => "out",
+            OpKind::AdjBp => "arb",
+            OpKind::Halt => "hlt",
+        };
+        f.pad(name)
+    }
+}
+
+/// A straight run of instructions, entered at `start` and left by a jump
+/// (taken or not) or a halt at the end of `start..end`.
+///
+/// Blocks are discovered at runtime, so a loop jumping into the middle of
+/// another block shows up as a separate, overlapping block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block {
+    pub start: i64,
+    pub end: i64,
+    /// How many times control entered the block.
+    pub entries: u64,
+    /// Instructions executed inside the block.
+    pub instrs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub total: u64,
+    pub by_addr: HashMap<i64, u64>,
+    pub by_kind: HashMap<OpKind, u64>,
+    /// Hottest first.
+    pub blocks: Vec<Block>,
+}
+
+const HOTTEST: usize = 10;
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = |n: u64| 100.0 * n as f64 / self.total.max(1) as f64;
+
+        writeln!(f, "{} instructions", self.total)?;
+        let mut kinds = self.by_kind.iter().collect::<Vec<_>>();
+        kinds.sort_by_key(|&(kind, n)| (std::cmp::Reverse(*n), *kind));
+        for (kind, &n) in kinds {
+            writeln!(f, "  {:<4} {:>12} {:>6.2}%", kind, n, percent(n))?;
+        }
+
+        writeln!(f, "hottest blocks:")?;
+        for block in self.blocks.iter().take(HOTTEST) {
+            writeln!(
+                f,
+                "  {:>5}..{:<5} {:>12} {:>6.2}% (entered {} times)",
+                block.start,
+                block.end,
+                block.instrs,
+                percent(block.instrs),
+                block.entries
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    total: u64,
+    by_addr: HashMap<i64, u64>,
+    by_kind: HashMap<OpKind, u64>,
+    blocks: HashMap<i64, Block>,
+    /// Start of the current block, `None` after leaving a block.
+    block: Option<i64>,
+    next_ip: i64,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    pub fn report(&self) -> Profile {
+        let mut blocks = self.blocks.values().copied().collect::<Vec<_>>();
+        blocks.sort_by_key(|it| (std::cmp::Reverse(it.instrs), it.start));
+        Profile {
+            total: self.total,
+            by_addr: self.by_addr.clone(),
+            by_kind: self.by_kind.clone(),
+            blocks,
+        }
+    }
+}
+
+impl Trace for Profiler {
+    fn instr(&mut self, ip: i64, _bp: i64, op: &Op) {
+        self.total += 1;
+        *self.by_addr.entry(ip).or_default() += 1;
+        *self.by_kind.entry(OpKind::of(op)).or_default() += 1;
+
+        // The debugger may move ip behind our back.
+        let start = match self.block {
+            Some(start) if ip == self.next_ip => start,
+            _ => ip,
+        };
+        let block = self.blocks.entry(start).or_insert(Block {
+            start,
+            end: start,
+            entries: 0,
+            instrs: 0,
+        });
+        if start == ip {
+            block.entries += 1;
+        }
+        block.instrs += 1;
+        block.end = block.end.max(ip + op.size());
+
+        self.next_ip = ip + op.size();
+        self.block = match op {
+            Op::Jump { .. } | Op::Halt => None,
+            _ => Some(start),
+        };
+    }
+}
+
+#[test]
+fn test_profile() {
+    use crate::{asm::assemble, IntCode, MemIo};
+
+    let prog = assemble(
+        "
+                in -> [n]
+        loop:   out [n]
+                add [n], #-1 -> [n]
+                jt [n], #loop
+                hlt
+        n:      .data 0
+        ",
+    )
+    .unwrap();
+    let mut cpu = IntCode::new(MemIo::new(vec![3]), prog);
+    let mut profiler = Profiler::new();
+    cpu.run_traced(&mut profiler).unwrap();
+    let profile = profiler.report();
+
+    assert_eq!(profile.total, 11);
+    assert_eq!(profile.by_addr[&0], 1);
+    assert_eq!(profile.by_addr[&8], 3);
+    assert_eq!(profile.by_kind[&OpKind::Output], 3);
+    assert_eq!(profile.by_kind[&OpKind::Jump(JumpOp::IfTrue)], 3);
+    assert_eq!(profile.by_kind.get(&OpKind::AdjBp), None);
+    let block = |start, end, entries, instrs| Block {
+        start,
+        end,
+        entries,
+        instrs,
+    };
+    assert_eq!(
+        profile.blocks,
+        vec![block(2, 11, 2, 6), block(0, 11, 1, 4), block(11, 12, 1, 1)]
+    );
+
+    let text = profile.to_string();
+    assert!(text.starts_with("11 instructions\n"), "{}", text);
+    assert!(
+        text.contains("\n      2..11               6  54.55% (entered 2 times)\n"),
+        "{}",
+        text
+    );
+}
@@ -0,0 +1,277 @@
+//! Recording of the values a machine reads and writes, and deterministic
+//! replay.
+//!
+//! A tape has one line per value, `iVALUE` for inputs and `oVALUE` for
+//! outputs, in the order they happened:
+//!
+//! ```text
+//! i5
+//! o25
+//! ```
+//!
+//! [`Recorder`] wraps any `Io` and writes its tape, [`Replay`] feeds the
+//! inputs of a tape back to a machine and checks that it writes the same
+//! outputs. Mismatches fail with a [`Divergence`], which the machine reports
+//! along with its `ip`.
+use std::{
+    error, fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{Error, Io, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeEvent {
+    Input(i64),
+    Output(i64),
+}
+
+impl fmt::Display for TapeEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapeEvent::Input(it) => write!(f, "i{}", it),
+            TapeEvent::Output(it) => write!(f, "o{}", it),
+        }
+    }
+}
+
+impl FromStr for TapeEvent {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<TapeEvent> {
+        let line = line.trim();
+        match line.get(..1) {
+            Some("i") => Ok(TapeEvent::Input(line[1..].parse()?)),
+            Some("o") => Ok(TapeEvent::Output(line[1..].parse()?)),
+            _ => Err(format!("invalid tape event: {:?}", line))?,
+        }
+    }
+}
+
+pub fn read_tape(input: impl BufRead) -> Result<Vec<TapeEvent>> {
+    input.lines().map(|line| line?.parse()).collect()
+}
+
+/// An `Io` which writes everything going through `io` to a tape.
+pub struct Recorder<IO, W: Write> {
+    io: IO,
+    out: W,
+}
+
+impl<IO> Recorder<IO, BufWriter<File>> {
+    pub fn create(io: IO, path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Recorder::new(io, BufWriter::new(file)))
+    }
+}
+
+impl<IO, W: Write> Recorder<IO, W> {
+    pub fn new(io: IO, out: W) -> Recorder<IO, W> {
+        Recorder { io, out }
+    }
+    /// Flushes the tape and returns the parts.
+    pub fn finish(mut self) -> io::Result<(IO, W)> {
+        self.out.flush()?;
+        Ok((self.io, self.out))
+    }
+}
+
+impl<IO: Io, W: Write> Io for Recorder<IO, W> {
+    fn read(&mut self) -> Result<Option<i64>> {
+        let res = self.io.read()?;
+        if let Some(value) = res {
+            writeln!(self.out, "{}", TapeEvent::Input(value))?;
+        }
+        Ok(res)
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        self.io.write(value)?;
+        writeln!(self.out, "{}", TapeEvent::Output(value))?;
+        Ok(())
+    }
+}
+
+/// What the machine did at a point of a replay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write(i64),
+    /// The machine stopped.
+    End,
+}
+
+/// The machine didn't do what the tape says.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the event on the tape.
+    pub step: usize,
+    /// The recorded event, `None` past the end of the tape.
+    pub expected: Option<TapeEvent>,
+    pub actual: Access,
+    /// Where the machine stopped, for [`Access::End`]. Other divergences are
+    /// wrapped in an [`IntcodeError::Io`](crate::IntcodeError::Io), which
+    /// has the ip.
+    pub ip: Option<i64>,
+}
+
+impl error::Error for Divergence {}
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "divergence at step {}: expected ", self.step)?;
+        match self.expected {
+            Some(TapeEvent::Input(it)) => write!(f, "input {}", it)?,
+            Some(TapeEvent::Output(it)) => write!(f, "output {}", it)?,
+            None => write!(f, "end of tape")?,
+        }
+        match self.actual {
+            Access::Read => write!(f, ", got input"),
+            Access::Write(it) => write!(f, ", got output {}", it),
+            Access::End => write!(f, ", got end of run"),
+        }?;
+        match self.ip {
+            Some(ip) => write!(f, " (ip = {})", ip),
+            None => Ok(()),
+        }
+    }
+}
+
+/// An `Io` which replays a tape. Reads past its end see the end of the
+/// input, as when recording a machine which stopped awaiting input.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    events: Vec<TapeEvent>,
+    pos: usize,
+}
+
+impl Replay {
+    pub fn new(events: Vec<TapeEvent>) -> Replay {
+        Replay { events, pos: 0 }
+    }
+    pub fn open(path: impl AsRef<Path>) -> Result<Replay> {
+        let text = fs::read_to_string(path)?;
+        Ok(Replay::new(read_tape(text.as_bytes())?))
+    }
+
+    /// Index of the next event.
+    pub fn step(&self) -> usize {
+        self.pos
+    }
+    /// Checks that the whole tape was replayed, once the machine stopped at
+    /// `ip`.
+    pub fn finish(&self, ip: i64) -> Result<(), Divergence> {
+        match self.events.get(self.pos) {
+            None => Ok(()),
+            Some(&event) => Err(Divergence {
+                ip: Some(ip),
+                ..self.divergence(Some(event), Access::End)
+            }),
+        }
+    }
+
+    fn divergence(&self, expected: Option<TapeEvent>, actual: Access) -> Divergence {
+        Divergence {
+            step: self.pos,
+            expected,
+            actual,
+            ip: None,
+        }
+    }
+}
+
+impl Io for Replay {
+    fn read(&mut self) -> Result<Option<i64>> {
+        match self.events.get(self.pos).copied() {
+            Some(TapeEvent::Input(value)) => {
+                self.pos += 1;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+            expected => Err(self.divergence(expected, Access::Read))?,
+        }
+    }
+    fn write(&mut self, value: i64) -> Result<()> {
+        match self.events.get(self.pos).copied() {
+            Some(TapeEvent::Output(it)) if it == value => {
+                self.pos += 1;
+                Ok(())
+            }
+            expected => Err(self.divergence(expected, Access::Write(value)))?,
+        }
+    }
+}
+
+#[test]
+fn test_tape() {
+    use crate::{IntCode, MemIo, Status};
+
+    // Outputs the squares of its inputs, until it reads 0.
+    let prog = crate::asm::assemble(
+        "
+        loop:   in -> [x]
+                jf [x], #end
+                mul [x], [x] -> [x]
+                out [x]
+                jt #1, #loop
+        end:    hlt
+        x:      .data 0
+        ",
+    )
+    .unwrap();
+    let io = Recorder::new(MemIo::new(vec![3, 4, 0]), Vec::new());
+    let mut cpu = IntCode::new(io, prog.clone());
+    cpu.run().unwrap();
+    let (io, tape) = cpu.io.finish().unwrap();
+    assert_eq!(io.into_output(), vec![9, 16]);
+    let tape = String::from_utf8(tape).unwrap();
+    assert_eq!(tape, "i3\no9\ni4\no16\ni0\n");
+
+    let events = read_tape(tape.as_bytes()).unwrap();
+    let mut cpu = IntCode::new(Replay::new(events.clone()), prog.clone());
+    cpu.run().unwrap();
+    cpu.io.finish(cpu.ip()).unwrap();
+
+    let check = |prog: Vec<i64>, events: Vec<TapeEvent>, expected: &str| {
+        let mut cpu = IntCode::new(Replay::new(events), prog);
+        let err = match cpu.run() {
+            Ok(_) => cpu.io.finish(cpu.ip()).unwrap_err().to_string(),
+            Err(err) => err.to_string(),
+        };
+        assert_eq!(err, expected);
+    };
+    let mut wrong = events.clone();
+    wrong[3] = TapeEvent::Output(17);
+    check(
+        prog.clone(),
+        wrong,
+        "io error: divergence at step 3: expected output 17, got output 16 (ip = 9, rb = 0)",
+    );
+    check(
+        prog.clone(),
+        events[..3].to_vec(),
+        "io error: divergence at step 3: expected end of tape, got output 16 (ip = 9, rb = 0)",
+    );
+    let mut early = events.clone();
+    early.push(TapeEvent::Output(0));
+    check(
+        prog.clone(),
+        early,
+        "divergence at step 5: expected output 0, got end of run (ip = 14)",
+    );
+    check(
+        prog.clone(),
+        vec![TapeEvent::Output(1)],
+        "io error: divergence at step 0: expected output 1, got input (ip = 0, rb = 0)",
+    );
+
+    // Running out of tape on a read stops the machine, like running out of
+    // input when recording.
+    let mut cpu = IntCode::new(Replay::new(events[..2].to_vec()), prog);
+    assert_eq!(cpu.run().unwrap(), Status::AwaitingInput);
+    cpu.io.finish(cpu.ip()).unwrap();
+
+    assert_eq!("o-5".parse::<TapeEvent>().unwrap(), TapeEvent::Output(-5));
+    assert!("x5".parse::<TapeEvent>().is_err());
+}
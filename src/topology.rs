@@ -0,0 +1,210 @@
+//! Networks of machines connected output to input.
+//!
+//! ```
+//! # use aoc::topology::Topology;
+//! // Two machines adding one to each value, in a pipeline.
+//! let inc = vec![3, 9, 1001, 9, 1, 9, 4, 9, 99, 0];
+//! let mut topo = Topology::new();
+//! let a = topo.add(inc.clone(), &[40]);
+//! let b = topo.add(inc, &[]);
+//! topo.connect(a, b);
+//! let out = topo.sink(b);
+//! assert_eq!(topo.run().unwrap().last(out), Some(42));
+//! ```
+//!
+//! Connections may form cycles. Machines are run one at a time, in the order
+//! they were added, each until it halts or blocks on an empty input queue,
+//! until none of them can make progress. So runs are deterministic.
+use std::{collections::VecDeque, error, fmt};
+
+use crate::{Event, IntCode, IntcodeError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SinkId(usize);
+
+#[derive(Debug)]
+pub struct TopologyError {
+    pub node: NodeId,
+    pub error: IntcodeError,
+}
+
+impl error::Error for TopologyError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "machine {}: {}", self.node.0, self.error)
+    }
+}
+
+struct Node {
+    cpu: IntCode<()>,
+    queue: VecDeque<i64>,
+    halted: bool,
+    /// Nodes receiving the outputs.
+    succs: Vec<NodeId>,
+    sinks: Vec<SinkId>,
+}
+
+#[derive(Default)]
+pub struct Topology {
+    nodes: Vec<Node>,
+    n_sinks: usize,
+}
+
+impl Topology {
+    pub fn new() -> Topology {
+        Topology::default()
+    }
+
+    /// Adds a machine running `program`, which reads `inputs` before
+    /// anything it receives.
+    pub fn add(&mut self, program: Vec<i64>, inputs: &[i64]) -> NodeId {
+        self.nodes.push(Node {
+            cpu: IntCode::new((), program),
+            queue: inputs.iter().copied().collect(),
+            halted: false,
+            succs: Vec::new(),
+            sinks: Vec::new(),
+        });
+        NodeId(self.nodes.len() - 1)
+    }
+    /// Sends the outputs of `from` to `to`. A machine connected to several
+    /// others sends every output to each of them.
+    pub fn connect(&mut self, from: NodeId, to: NodeId) {
+        self.nodes[from.0].succs.push(to)
+    }
+    /// Records the outputs of `from`.
+    pub fn sink(&mut self, from: NodeId) -> SinkId {
+        let res = SinkId(self.n_sinks);
+        self.n_sinks += 1;
+        self.nodes[from.0].sinks.push(res);
+        res
+    }
+
+    /// Runs the machines until all of them are halted or waiting for input.
+    pub fn run(mut self) -> Result<Outcome, TopologyError> {
+        let mut sinks = vec![Vec::new(); self.n_sinks];
+        loop {
+            let mut progress = false;
+            for idx in 0..self.nodes.len() {
+                progress |= self.run_node(idx, &mut sinks)?;
+            }
+            if !progress {
+                break;
+            }
+        }
+        let halted = self.nodes.iter().map(|it| it.halted).collect();
+        Ok(Outcome { sinks, halted })
+    }
+
+    /// Runs a machine until it blocks, returns whether it did anything.
+    fn run_node(&mut self, idx: usize, sinks: &mut [Vec<i64>]) -> Result<bool, TopologyError> {
+        let mut progress = false;
+        let node = NodeId(idx);
+        while !self.nodes[idx].halted {
+            let event = self.nodes[idx].cpu.run_until_event();
+            match event.map_err(|error| TopologyError { node, error })? {
+                Event::NeedsInput => match self.nodes[idx].queue.pop_front() {
+                    Some(value) => {
+                        let res = self.nodes[idx].cpu.provide_input(value);
+                        res.map_err(|error| TopologyError { node, error })?
+                    }
+                    None => break,
+                },
+                Event::Output(value) => {
+                    for i in 0..self.nodes[idx].succs.len() {
+                        let succ = self.nodes[idx].succs[i];
+                        self.nodes[succ.0].queue.push_back(value);
+                    }
+                    for sink in self.nodes[idx].sinks.iter() {
+                        sinks[sink.0].push(value);
+                    }
+                }
+                Event::Halted => self.nodes[idx].halted = true,
+            }
+            progress = true;
+        }
+        Ok(progress)
+    }
+}
+
+/// The state of a [`Topology`] which ran to quiescence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    sinks: Vec<Vec<i64>>,
+    halted: Vec<bool>,
+}
+
+impl Outcome {
+    /// All values output to `sink`.
+    pub fn values(&self, sink: SinkId) -> &[i64] {
+        &self.sinks[sink.0]
+    }
+    pub fn last(&self, sink: SinkId) -> Option<i64> {
+        self.values(sink).last().copied()
+    }
+    /// Whether `node` halted, rather than waiting for more input.
+    pub fn is_halted(&self, node: NodeId) -> bool {
+        self.halted[node.0]
+    }
+}
+
+#[test]
+fn test_topology() {
+    // The day7 feedback loop.
+    let prog = vec![
+        3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28,
+        1005, 28, 6, 99, 0, 0, 5,
+    ];
+    let mut topo = Topology::new();
+    let amps = [9, 8, 7, 6, 5]
+        .iter()
+        .enumerate()
+        .map(|(i, &phase)| {
+            let inputs = if i == 0 { vec![phase, 0] } else { vec![phase] };
+            topo.add(prog.clone(), &inputs)
+        })
+        .collect::<Vec<_>>();
+    for i in 0..amps.len() {
+        topo.connect(amps[i], amps[(i + 1) % amps.len()]);
+    }
+    let thrust = topo.sink(amps[4]);
+    let res = topo.run().unwrap();
+    assert_eq!(res.last(thrust), Some(139629729));
+    assert_eq!(res.values(thrust).len(), 5);
+    assert!(amps.iter().all(|&it| res.is_halted(it)));
+
+    // Fan-out to a doubler and an echo that waits forever, and fan-in.
+    let double = vec![3, 11, 1002, 11, 2, 11, 4, 11, 1105, 1, 0, 0];
+    let echo = vec![3, 7, 4, 7, 1105, 1, 0, 0];
+    let mut topo = Topology::new();
+    let src = topo.add(vec![104, 1, 104, 2, 99], &[]);
+    let (a, b) = (topo.add(double, &[]), topo.add(echo.clone(), &[10]));
+    topo.connect(src, a);
+    topo.connect(src, b);
+    let merged = topo.add(echo, &[]);
+    topo.connect(a, merged);
+    topo.connect(b, merged);
+    let (all, doubled) = (topo.sink(merged), topo.sink(a));
+    let res = topo.run().unwrap();
+    assert_eq!(res.values(doubled), &[2, 4]);
+    assert_eq!(res.values(all), &[2, 4, 10, 1, 2]);
+    assert!(res.is_halted(src));
+    assert!(!res.is_halted(a));
+
+    let mut topo = Topology::new();
+    topo.add(vec![99], &[]);
+    topo.add(vec![42], &[]);
+    let err = topo.run().unwrap_err();
+    assert_eq!(err.node, NodeId(1));
+    assert_eq!(
+        err.to_string(),
+        "machine 1: invalid op code: 42 (ip = 0, rb = 0)"
+    );
+}
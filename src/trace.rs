@@ -0,0 +1,251 @@
+//! Recording of executed instructions to a trace file.
+//!
+//! A trace has one line per executed instruction:
+//!
+//! ```text
+//! 3 4 0 1001,12,-1,12 3,-1 w12=2
+//! ```
+//!
+//! The fields are the step number, `ip`, the relative base, the encoded
+//! instruction, the operand values it read (`-` if none), and its effects:
+//! memory writes `wADDR=VALUE`, inputs `iVALUE` and outputs `oVALUE`.
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{Error, Op, Result, Trace};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub step: u64,
+    pub ip: i64,
+    pub bp: i64,
+    pub op: Op,
+    pub args: Vec<i64>,
+    pub writes: Vec<(i64, i64)>,
+    pub input: Option<i64>,
+    pub output: Option<i64>,
+}
+
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} ", self.step, self.ip, self.bp)?;
+        write!(f, "{}", join(&self.op.encode()))?;
+        if self.args.is_empty() {
+            write!(f, " -")?;
+        } else {
+            write!(f, " {}", join(&self.args))?;
+        }
+        for (addr, value) in self.writes.iter() {
+            write!(f, " w{}={}", addr, value)?;
+        }
+        if let Some(value) = self.input {
+            write!(f, " i{}", value)?;
+        }
+        if let Some(value) = self.output {
+            write!(f, " o{}", value)?;
+        }
+        Ok(())
+    }
+}
+
+fn join(values: &[i64]) -> String {
+    values
+        .iter()
+        .map(|it| it.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl FromStr for TraceRecord {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<TraceRecord> {
+        fn list(text: &str) -> Result<Vec<i64>> {
+            if text == "-" {
+                return Ok(Vec::new());
+            }
+            let res = text
+                .split(',')
+                .map(|it| it.parse())
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(res)
+        }
+
+        let mut fields = line.split_whitespace();
+        let mut field = || fields.next().ok_or("truncated trace record");
+        let step = field()?.parse()?;
+        let ip = field()?.parse()?;
+        let bp = field()?.parse()?;
+        let words = list(field()?)?;
+        let (_, op) = Op::decode(ip, bp, |addr| words.get((addr - ip) as usize).copied())?;
+        let args = list(field()?)?;
+
+        let mut res = TraceRecord {
+            step,
+            ip,
+            bp,
+            op,
+            args,
+            writes: Vec::new(),
+            input: None,
+            output: None,
+        };
+        for effect in fields {
+            let value = effect.get(1..).unwrap_or("");
+            match effect.get(..1) {
+                Some("w") => {
+                    let mut parts = value.splitn(2, '=');
+                    let addr = parts.next().unwrap().parse()?;
+                    let value = parts.next().ok_or("invalid write")?.parse()?;
+                    res.writes.push((addr, value));
+                }
+                Some("i") => res.input = Some(value.parse()?),
+                Some("o") => res.output = Some(value.parse()?),
+                _ => Err(format!("unknown trace effect: {}", effect))?,
+            }
+        }
+        Ok(res)
+    }
+}
+
+pub fn read_trace(input: impl BufRead) -> impl Iterator<Item = Result<TraceRecord>> {
+    input.lines().map(|line| line?.parse())
+}
+
+/// A [`Trace`] which writes every executed instruction to `out`.
+///
+/// Write errors are remembered and reported by [`TraceWriter::finish`].
+pub struct TraceWriter<W: Write> {
+    out: W,
+    step: u64,
+    record: Option<TraceRecord>,
+    error: Option<io::Error>,
+}
+
+impl TraceWriter<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(TraceWriter::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(out: W) -> TraceWriter<W> {
+        TraceWriter {
+            out,
+            step: 0,
+            record: None,
+            error: None,
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_record();
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn flush_record(&mut self) {
+        if let Some(record) = self.record.take() {
+            if self.error.is_none() {
+                if let Err(err) = writeln!(self.out, "{}", record) {
+                    self.error = Some(err);
+                }
+            }
+        }
+    }
+
+    fn record(&mut self) -> &mut TraceRecord {
+        self.record.as_mut().expect("effect before instruction")
+    }
+}
+
+impl<W: Write> Trace for TraceWriter<W> {
+    fn instr(&mut self, ip: i64, bp: i64, op: &Op) {
+        self.flush_record();
+        self.record = Some(TraceRecord {
+            step: self.step,
+            ip,
+            bp,
+            op: *op,
+            args: Vec::new(),
+            writes: Vec::new(),
+            input: None,
+            output: None,
+        });
+        self.step += 1;
+    }
+    fn arg(&mut self, value: i64) {
+        self.record().args.push(value)
+    }
+    fn store(&mut self, addr: i64, _old: i64, new: i64) {
+        self.record().writes.push((addr, new))
+    }
+    fn input(&mut self, value: i64) {
+        self.record().input = Some(value)
+    }
+    fn output(&mut self, value: i64) {
+        self.record().output = Some(value)
+    }
+}
+
+#[test]
+fn test_trace() {
+    use crate::{asm::assemble, IntCode, MemIo};
+
+    let prog = assemble(
+        "
+                in -> [n]
+        loop:   out [n]
+                add [n], #-1 -> [n]
+                jt [n], #loop
+                hlt
+        n:      .data 0
+        ",
+    )
+    .unwrap();
+    let mut cpu = IntCode::new(MemIo::new(vec![2]), prog);
+    let mut trace = TraceWriter::new(Vec::new());
+    cpu.run_traced(&mut trace).unwrap();
+    let text = String::from_utf8(trace.finish().unwrap()).unwrap();
+
+    let expected = "\
+0 0 0 3,12 - w12=2 i2
+1 2 0 4,12 2 o2
+2 4 0 1001,12,-1,12 2,-1 w12=1
+3 8 0 1005,12,2 1,2
+4 2 0 4,12 1 o1
+5 4 0 1001,12,-1,12 1,-1 w12=0
+6 8 0 1005,12,2 0
+7 11 0 99 -
+";
+    assert_eq!(text, expected);
+
+    let records = read_trace(text.as_bytes())
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(records.len(), 8);
+    assert_eq!(records[2].op.to_string(), "add [12], #-1 -> [12]");
+    assert_eq!(records[2].writes, vec![(12, 1)]);
+    assert_eq!(records[4].output, Some(1));
+    for (record, line) in records.iter().zip(text.lines()) {
+        assert_eq!(record.to_string(), line);
+    }
+
+    let err = "1 2 0 4,12 2 \u{e9}2".parse::<TraceRecord>().unwrap_err();
+    assert_eq!(err.to_string(), "unknown trace effect: \u{e9}2");
+    let line = format!("0 {} 0 1,0,0,0 -", i64::MAX);
+    let err = line.parse::<TraceRecord>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("ip outside of memory (ip = {}, rb = 0)", i64::MAX)
+    );
+}
@@ -0,0 +1,326 @@
+//! Ahead-of-time translation of Intcode images to Rust.
+//!
+//! Every instruction found by [`cfg::instructions`] becomes an arm of a
+//! `match ip` with its operands and addressing modes resolved at translation
+//! time:
+//!
+//! ```text
+//! pub fn prog<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError>
+//! ```
+//!
+//! runs the image with `io` and returns the stopped machine, exactly like
+//! `IntCode::run` would leave it. Whatever the translated code can't handle
+//! (jumps to addresses that weren't translated, writes into translated
+//! instructions, invalid addresses) is handed over to the interpreter, which
+//! keeps going until it reaches a translated instruction that is still
+//! intact. So self-modifying regions run interpreted, and faults are
+//! reported by the interpreter itself.
+use std::fmt::Write;
+
+use crate::{
+    cfg, ArithOp, IntCode, IntcodeError, Io, JumpOp, Memory, Op, Status, Trace, Value,
+    VEC_MEM_LIMIT,
+};
+
+/// Translates `image` into a Rust function called `name`.
+pub fn transpile(image: &[i64], name: &str) -> String {
+    let arms = cfg::instructions(image);
+    let mut res = String::new();
+    let w = &mut res;
+
+    wln(
+        w,
+        0,
+        &format!("/// Transpiled from a {} cell Intcode image.", image.len()),
+    );
+    wln(
+        w,
+        0,
+        "#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]",
+    );
+    wln(
+        w,
+        0,
+        &format!(
+            "pub fn {}<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {{",
+            name
+        ),
+    );
+    wln(w, 1, &format!("const IMAGE: &[i64] = &[{}];", join(image)));
+    let sizes = arms
+        .iter()
+        .map(|(ip, (size, _))| format!("({}, {})", ip, size))
+        .collect::<Vec<_>>();
+    wln(
+        w,
+        1,
+        &format!("const ARMS: &[(i64, i64)] = &[{}];", sizes.join(", ")),
+    );
+    wln(
+        w,
+        1,
+        "let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);",
+    );
+    wln(w, 1, "let mut ip: i64 = 0;");
+    wln(w, 1, "let mut bp: i64 = 0;");
+    res += MACROS;
+
+    let w = &mut res;
+    wln(w, 1, "loop {");
+    wln(w, 2, "if !rt.is_live(ip) {");
+    wln(w, 3, "bail!();");
+    wln(w, 2, "}");
+    wln(w, 2, "match ip {");
+    for (&ip, &(size, op)) in arms.iter() {
+        wln(w, 3, &format!("{} => {{", ip));
+        wln(w, 4, &format!("// {}", op));
+        arm(w, ip + size, op);
+        wln(w, 3, "}");
+    }
+    wln(w, 3, "_ => unreachable!(),");
+    wln(w, 2, "}");
+    wln(w, 1, "}");
+    wln(w, 0, "}");
+    res
+}
+
+const MACROS: &str = "    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+";
+
+fn arm(w: &mut String, next: i64, op: Op) {
+    let value = |v: Value| match v {
+        Value::Immediate(it) => it.to_string(),
+        Value::Addr(it) => format!("load!({})", addr(it)),
+    };
+    match op {
+        Op::Halt => wln(w, 4, "bail!();"),
+        Op::Arith { op, lhs, rhs, dst } => {
+            // Overflows are left to the interpreter and its policy.
+            let expr = match op {
+                ArithOp::Add => {
+                    format!("checked!(i64::checked_add({}, {}))", value(lhs), value(rhs))
+                }
+                ArithOp::Mul => {
+                    format!("checked!(i64::checked_mul({}, {}))", value(lhs), value(rhs))
+                }
+                ArithOp::LessThan => format!("({} < {}) as i64", value(lhs), value(rhs)),
+                ArithOp::Equals => format!("({} == {}) as i64", value(lhs), value(rhs)),
+            };
+            wln(w, 4, &format!("let value = {};", expr));
+            wln(w, 4, &format!("if !rt.store({}, value) {{", addr(dst)));
+            wln(w, 5, "bail!();");
+            wln(w, 4, "}");
+        }
+        Op::Input { dst } => {
+            // Check first, the interpreter must not read the value again.
+            wln(w, 4, &format!("if !rt.writable({}) {{", addr(dst)));
+            wln(w, 5, "bail!();");
+            wln(w, 4, "}");
+            wln(w, 4, "let value = match rt.read(ip, bp)? {");
+            wln(w, 5, "Some(it) => it,");
+            wln(w, 5, "None => return Ok(rt.awaiting_input(ip, bp)),");
+            wln(w, 4, "};");
+            wln(w, 4, &format!("rt.store({}, value);", addr(dst)));
+        }
+        Op::Output { src } => {
+            wln(w, 4, &format!("let value = {};", value(src)));
+            wln(w, 4, "rt.write(ip, bp, value)?;");
+        }
+        Op::Jump { op, cond, tgt } => {
+            let cmp = match op {
+                JumpOp::IfTrue => "!=",
+                JumpOp::IfFalse => "==",
+            };
+            wln(w, 4, &format!("if {} {} 0 {{", value(cond), cmp));
+            wln(w, 5, &format!("ip = {};", value(tgt)));
+            wln(w, 5, "continue;");
+            wln(w, 4, "}");
+        }
+        Op::AdjBp { adj } => wln(
+            w,
+            4,
+            &format!("bp = checked!(i64::checked_add(bp, {}));", value(adj)),
+        ),
+    }
+    if op != Op::Halt {
+        wln(w, 4, &format!("ip = {};", next));
+    }
+}
+
+fn addr(addr: crate::Addr) -> String {
+    match (addr.rel, addr.value) {
+        (false, value) => value.to_string(),
+        (true, value) => format!("checked!(i64::checked_add(bp, {}))", value),
+    }
+}
+
+fn wln(w: &mut String, indent: usize, line: &str) {
+    writeln!(w, "{:indent$}{}", "", line, indent = indent * 4).unwrap();
+}
+
+fn join(values: &[i64]) -> String {
+    values
+        .iter()
+        .map(|it| it.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Support code for transpiled programs, not meant to be used directly.
+pub struct Runtime<IO> {
+    cpu: IntCode<IO>,
+    /// Size of the translated instruction at each address, 0 if there is
+    /// none or it was overwritten.
+    arms: Vec<i64>,
+    /// Cells belonging to translated instructions.
+    code: Vec<bool>,
+    /// Why the interpreter stopped.
+    status: Status,
+}
+
+/// Collects the addresses written by the interpreter.
+struct Stores(Vec<i64>);
+
+impl Trace for Stores {
+    fn store(&mut self, addr: i64, _old: i64, _new: i64) {
+        self.0.push(addr)
+    }
+}
+
+impl<IO: Io> Runtime<IO> {
+    pub fn new(io: IO, image: &[i64], arms: &[(i64, i64)]) -> Runtime<IO> {
+        let mut res = Runtime {
+            cpu: IntCode::new(io, image.to_vec()),
+            arms: vec![0; image.len()],
+            code: vec![false; image.len()],
+            status: Status::Halted,
+        };
+        for &(ip, size) in arms {
+            res.arms[ip as usize] = size;
+            for addr in ip..ip + size {
+                if let Some(it) = res.code.get_mut(addr as usize) {
+                    *it = true;
+                }
+            }
+        }
+        res
+    }
+
+    pub fn is_live(&self, ip: i64) -> bool {
+        ip >= 0 && matches!(self.arms.get(ip as usize), Some(&size) if size > 0)
+    }
+    /// `None` for cells the interpreter has to load, including widened ones.
+    pub fn load(&self, addr: i64) -> Option<i64> {
+        if addr < 0 {
+            return None;
+        }
+        let value = self.cpu.mem.cell(addr as usize)?;
+        if !self.cpu.wide.is_empty() && self.cpu.widened(addr, value).is_some() {
+            return None;
+        }
+        Some(value)
+    }
+    pub fn writable(&self, addr: i64) -> bool {
+        addr >= 0
+            && (addr as usize) < VEC_MEM_LIMIT
+            && !self.code.get(addr as usize).copied().unwrap_or(false)
+    }
+    pub fn store(&mut self, addr: i64, value: i64) -> bool {
+        if !self.writable(addr) {
+            return false;
+        }
+        match self.cpu.mem.cell_mut(addr as usize) {
+            Some(cell) => {
+                *cell = value;
+                if !self.cpu.wide.is_empty() {
+                    self.cpu.wide.remove(&addr);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn read(&mut self, ip: i64, bp: i64) -> Result<Option<i64>, IntcodeError> {
+        let res = self.cpu.io.read();
+        res.map_err(|e| self.fault(ip, bp, e))
+    }
+    pub fn write(&mut self, ip: i64, bp: i64, value: i64) -> Result<(), IntcodeError> {
+        let res = self.cpu.io.write(value);
+        res.map_err(|e| self.fault(ip, bp, e))
+    }
+
+    /// Interprets at least one instruction at `ip`, until reaching an intact
+    /// translated one. Returns `None` if the machine stopped.
+    pub fn interpret(&mut self, ip: i64, bp: i64) -> Result<Option<(i64, i64)>, IntcodeError> {
+        self.cpu.ip = ip;
+        self.cpu.bp = bp;
+        let mut stores = Stores(Vec::new());
+        loop {
+            let stop = self.cpu.advance(&mut stores)?;
+            for addr in stores.0.drain(..) {
+                self.evict(addr);
+            }
+            if let Some(status) = stop {
+                self.status = status;
+                return Ok(None);
+            }
+            if self.is_live(self.cpu.ip) {
+                return Ok(Some((self.cpu.ip, self.cpu.bp)));
+            }
+        }
+    }
+    /// Returns the machine once the interpreter stopped it.
+    pub fn finish(self) -> (Status, IntCode<IO>) {
+        (self.status, self.cpu)
+    }
+    /// Returns the machine blocked on the input instruction at `ip`.
+    pub fn awaiting_input(mut self, ip: i64, bp: i64) -> (Status, IntCode<IO>) {
+        self.cpu.ip = ip;
+        self.cpu.bp = bp;
+        (Status::AwaitingInput, self.cpu)
+    }
+
+    fn evict(&mut self, addr: i64) {
+        if !self.code.get(addr as usize).copied().unwrap_or(false) {
+            return;
+        }
+        for ip in (addr - 3).max(0)..=addr {
+            let size = &mut self.arms[ip as usize];
+            if ip + *size > addr {
+                *size = 0;
+            }
+        }
+    }
+    fn fault(&mut self, ip: i64, bp: i64, error: crate::Error) -> IntcodeError {
+        self.cpu.ip = ip;
+        self.cpu.bp = bp;
+        self.cpu.io_error(ip, error)
+    }
+}
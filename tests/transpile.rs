@@ -0,0 +1,173 @@
+//! Checks that transpiled example programs behave exactly like the
+//! interpreter.
+//!
+//! The translations are checked in as `transpiled/examples.rs`, run the tests
+//! with `UPDATE_EXPECT=1` to regenerate them.
+use std::fs;
+
+use aoc::{transpile::transpile, IntCode, IntcodeError, MemIo, Status};
+
+mod transpiled {
+    include!("transpiled/examples.rs");
+}
+
+type Transpiled = fn(MemIo) -> Result<(Status, IntCode<MemIo>), IntcodeError>;
+type Example = (
+    &'static str,
+    &'static [i64],
+    &'static [&'static [i64]],
+    Transpiled,
+);
+
+/// Example programs from the puzzles, with the inputs to try.
+const EXAMPLES: &[Example] = &[
+    (
+        "day2_a",
+        &[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+        &[&[]],
+        transpiled::day2_a,
+    ),
+    ("day2_b", &[1, 0, 0, 0, 99], &[&[]], transpiled::day2_b),
+    ("day2_c", &[2, 3, 0, 3, 99], &[&[]], transpiled::day2_c),
+    ("day2_d", &[2, 4, 4, 5, 99, 0], &[&[]], transpiled::day2_d),
+    (
+        "day2_e",
+        &[1, 1, 1, 4, 99, 5, 6, 0, 99],
+        &[&[]],
+        transpiled::day2_e,
+    ),
+    (
+        "day5_a",
+        &[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8],
+        &[&[0], &[7], &[8], &[92]],
+        transpiled::day5_a,
+    ),
+    (
+        "day5_b",
+        &[3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8],
+        &[&[0], &[7], &[8], &[92]],
+        transpiled::day5_b,
+    ),
+    (
+        "day5_c",
+        &[3, 3, 1108, -1, 8, 3, 4, 3, 99],
+        &[&[0], &[7], &[8], &[92]],
+        transpiled::day5_c,
+    ),
+    (
+        "day5_d",
+        &[3, 3, 1107, -1, 8, 3, 4, 3, 99],
+        &[&[0], &[7], &[8], &[92]],
+        transpiled::day5_d,
+    ),
+    (
+        "day5_e",
+        &[3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9],
+        &[&[-1], &[0], &[1], &[92]],
+        transpiled::day5_e,
+    ),
+    (
+        "day5_f",
+        &[3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1],
+        &[&[-1], &[0], &[1], &[92]],
+        transpiled::day5_f,
+    ),
+    (
+        "day5_g",
+        &[
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ],
+        &[&[0], &[7], &[8], &[92]],
+        transpiled::day5_g,
+    ),
+    (
+        "day7_a",
+        &[
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ],
+        &[&[9, 0], &[9, 0, 5, 7, 1, 3, 2, 8, 4]],
+        transpiled::day7_a,
+    ),
+    (
+        "day7_b",
+        &[
+            3, 52, 1001, 52, -5, 52, 3, 53, 1, 52, 56, 54, 1007, 54, 5, 55, 1005, 55, 26, 1001, 54,
+            -5, 54, 1105, 1, 12, 1, 53, 54, 53, 1008, 54, 0, 55, 1001, 55, 1, 55, 2, 53, 55, 53, 4,
+            53, 1001, 56, -1, 56, 1005, 56, 6, 99, 0, 0, 0, 0, 10,
+        ],
+        &[&[9, 0], &[7, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]],
+        transpiled::day7_b,
+    ),
+    (
+        "day9_a",
+        &[
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ],
+        &[&[]],
+        transpiled::day9_a,
+    ),
+    (
+        "day9_b",
+        &[1102, 34915192, 34915192, 7, 4, 7, 99, 0],
+        &[&[]],
+        transpiled::day9_b,
+    ),
+    (
+        "day9_c",
+        &[104, 1125899906842624, 99],
+        &[&[]],
+        transpiled::day9_c,
+    ),
+    (
+        "overflow",
+        &[3, 9, 1002, 9, 3, 9, 4, 9, 99, 0],
+        &[&[5], &[i64::MAX / 3], &[i64::MAX / 2], &[i64::MIN], &[]],
+        transpiled::overflow,
+    ),
+];
+
+type Outcome = Result<(Status, Vec<i64>, i64, i64, Vec<i64>), String>;
+
+fn outcome(res: Result<(Status, IntCode<MemIo>), IntcodeError>) -> Outcome {
+    let (status, cpu) = res.map_err(|e| e.to_string())?;
+    Ok((
+        status,
+        cpu.mem().clone(),
+        cpu.ip(),
+        cpu.bp(),
+        cpu.io.into_output(),
+    ))
+}
+
+#[test]
+fn test_examples() {
+    for &(name, image, inputs, transpiled) in EXAMPLES {
+        for input in inputs {
+            let mut cpu = IntCode::new(MemIo::new(input.to_vec()), image.to_vec());
+            let expected = outcome(cpu.run().map(|status| (status, cpu)));
+            let actual = outcome(transpiled(MemIo::new(input.to_vec())));
+            assert_eq!(actual, expected, "{} with input {:?}", name, input);
+        }
+    }
+}
+
+#[test]
+fn test_up_to_date() {
+    let expected = EXAMPLES
+        .iter()
+        .map(|&(name, image, ..)| transpile(image, name))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/transpiled/examples.rs");
+    let actual = fs::read_to_string(path).unwrap_or_default();
+    if actual != expected {
+        if std::env::var_os("UPDATE_EXPECT").is_some() {
+            fs::write(path, expected).unwrap();
+        } else {
+            panic!("{} is out of date, rerun with UPDATE_EXPECT=1", path);
+        }
+    }
+}
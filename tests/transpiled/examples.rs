@@ -0,0 +1,1498 @@
+/// Transpiled from a 12 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day2_a<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+    const ARMS: &[(i64, i64)] = &[(0, 4), (4, 4), (8, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // add [9], [10] -> [3]
+                let value = checked!(i64::checked_add(load!(9), load!(10)));
+                if !rt.store(3, value) {
+                    bail!();
+                }
+                ip = 4;
+            }
+            4 => {
+                // mul [3], [11] -> [0]
+                let value = checked!(i64::checked_mul(load!(3), load!(11)));
+                if !rt.store(0, value) {
+                    bail!();
+                }
+                ip = 8;
+            }
+            8 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 5 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day2_b<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[1, 0, 0, 0, 99];
+    const ARMS: &[(i64, i64)] = &[(0, 4), (4, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // add [0], [0] -> [0]
+                let value = checked!(i64::checked_add(load!(0), load!(0)));
+                if !rt.store(0, value) {
+                    bail!();
+                }
+                ip = 4;
+            }
+            4 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 5 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day2_c<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[2, 3, 0, 3, 99];
+    const ARMS: &[(i64, i64)] = &[(0, 4), (4, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // mul [3], [0] -> [3]
+                let value = checked!(i64::checked_mul(load!(3), load!(0)));
+                if !rt.store(3, value) {
+                    bail!();
+                }
+                ip = 4;
+            }
+            4 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 6 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day2_d<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[2, 4, 4, 5, 99, 0];
+    const ARMS: &[(i64, i64)] = &[(0, 4), (4, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // mul [4], [4] -> [5]
+                let value = checked!(i64::checked_mul(load!(4), load!(4)));
+                if !rt.store(5, value) {
+                    bail!();
+                }
+                ip = 4;
+            }
+            4 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 9 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day2_e<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[1, 1, 1, 4, 99, 5, 6, 0, 99];
+    const ARMS: &[(i64, i64)] = &[(0, 4), (4, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // add [1], [1] -> [4]
+                let value = checked!(i64::checked_add(load!(1), load!(1)));
+                if !rt.store(4, value) {
+                    bail!();
+                }
+                ip = 4;
+            }
+            4 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 11 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day5_a<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 4), (6, 2), (8, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [9]
+                if !rt.writable(9) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(9, value);
+                ip = 2;
+            }
+            2 => {
+                // eq [9], [10] -> [9]
+                let value = (load!(9) == load!(10)) as i64;
+                if !rt.store(9, value) {
+                    bail!();
+                }
+                ip = 6;
+            }
+            6 => {
+                // out [9]
+                let value = load!(9);
+                rt.write(ip, bp, value)?;
+                ip = 8;
+            }
+            8 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 11 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day5_b<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 4), (6, 2), (8, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [9]
+                if !rt.writable(9) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(9, value);
+                ip = 2;
+            }
+            2 => {
+                // lt [9], [10] -> [9]
+                let value = (load!(9) < load!(10)) as i64;
+                if !rt.store(9, value) {
+                    bail!();
+                }
+                ip = 6;
+            }
+            6 => {
+                // out [9]
+                let value = load!(9);
+                rt.write(ip, bp, value)?;
+                ip = 8;
+            }
+            8 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 9 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day5_c<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 3, 1108, -1, 8, 3, 4, 3, 99];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 4), (6, 2), (8, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [3]
+                if !rt.writable(3) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(3, value);
+                ip = 2;
+            }
+            2 => {
+                // eq #-1, #8 -> [3]
+                let value = (-1 == 8) as i64;
+                if !rt.store(3, value) {
+                    bail!();
+                }
+                ip = 6;
+            }
+            6 => {
+                // out [3]
+                let value = load!(3);
+                rt.write(ip, bp, value)?;
+                ip = 8;
+            }
+            8 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 9 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day5_d<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 3, 1107, -1, 8, 3, 4, 3, 99];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 4), (6, 2), (8, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [3]
+                if !rt.writable(3) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(3, value);
+                ip = 2;
+            }
+            2 => {
+                // lt #-1, #8 -> [3]
+                let value = (-1 < 8) as i64;
+                if !rt.store(3, value) {
+                    bail!();
+                }
+                ip = 6;
+            }
+            6 => {
+                // out [3]
+                let value = load!(3);
+                rt.write(ip, bp, value)?;
+                ip = 8;
+            }
+            8 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 16 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day5_e<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 3), (5, 4), (9, 2), (11, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [12]
+                if !rt.writable(12) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(12, value);
+                ip = 2;
+            }
+            2 => {
+                // jf [12], [15]
+                if load!(12) == 0 {
+                    ip = load!(15);
+                    continue;
+                }
+                ip = 5;
+            }
+            5 => {
+                // add [13], [14] -> [13]
+                let value = checked!(i64::checked_add(load!(13), load!(14)));
+                if !rt.store(13, value) {
+                    bail!();
+                }
+                ip = 9;
+            }
+            9 => {
+                // out [13]
+                let value = load!(13);
+                rt.write(ip, bp, value)?;
+                ip = 11;
+            }
+            11 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 13 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day5_f<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 3), (9, 2), (11, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [3]
+                if !rt.writable(3) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(3, value);
+                ip = 2;
+            }
+            2 => {
+                // jt #-1, #9
+                if -1 != 0 {
+                    ip = 9;
+                    continue;
+                }
+                ip = 5;
+            }
+            9 => {
+                // out [12]
+                let value = load!(12);
+                rt.write(ip, bp, value)?;
+                ip = 11;
+            }
+            11 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 47 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day5_g<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20, 1105, 1, 46, 98, 99];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 4), (6, 3), (9, 4), (13, 3), (16, 3), (22, 4), (26, 2), (28, 3), (31, 2), (33, 3), (36, 4), (40, 2), (42, 3), (46, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [21]
+                if !rt.writable(21) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(21, value);
+                ip = 2;
+            }
+            2 => {
+                // eq [21], #8 -> [20]
+                let value = (load!(21) == 8) as i64;
+                if !rt.store(20, value) {
+                    bail!();
+                }
+                ip = 6;
+            }
+            6 => {
+                // jt [20], #22
+                if load!(20) != 0 {
+                    ip = 22;
+                    continue;
+                }
+                ip = 9;
+            }
+            9 => {
+                // lt #8, [21] -> [20]
+                let value = (8 < load!(21)) as i64;
+                if !rt.store(20, value) {
+                    bail!();
+                }
+                ip = 13;
+            }
+            13 => {
+                // jf [20], #31
+                if load!(20) == 0 {
+                    ip = 31;
+                    continue;
+                }
+                ip = 16;
+            }
+            16 => {
+                // jf #0, #36
+                if 0 == 0 {
+                    ip = 36;
+                    continue;
+                }
+                ip = 19;
+            }
+            22 => {
+                // mul [21], #125 -> [20]
+                let value = checked!(i64::checked_mul(load!(21), 125));
+                if !rt.store(20, value) {
+                    bail!();
+                }
+                ip = 26;
+            }
+            26 => {
+                // out [20]
+                let value = load!(20);
+                rt.write(ip, bp, value)?;
+                ip = 28;
+            }
+            28 => {
+                // jt #1, #46
+                if 1 != 0 {
+                    ip = 46;
+                    continue;
+                }
+                ip = 31;
+            }
+            31 => {
+                // out #999
+                let value = 999;
+                rt.write(ip, bp, value)?;
+                ip = 33;
+            }
+            33 => {
+                // jt #1, #46
+                if 1 != 0 {
+                    ip = 46;
+                    continue;
+                }
+                ip = 36;
+            }
+            36 => {
+                // add #1000, #1 -> [20]
+                let value = checked!(i64::checked_add(1000, 1));
+                if !rt.store(20, value) {
+                    bail!();
+                }
+                ip = 40;
+            }
+            40 => {
+                // out [20]
+                let value = load!(20);
+                rt.write(ip, bp, value)?;
+                ip = 42;
+            }
+            42 => {
+                // jt #1, #46
+                if 1 != 0 {
+                    ip = 46;
+                    continue;
+                }
+                ip = 45;
+            }
+            46 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 29 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day7_a<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28, 1005, 28, 6, 99, 0, 0, 5];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 4), (6, 2), (8, 4), (12, 4), (16, 2), (18, 4), (22, 3), (25, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [26]
+                if !rt.writable(26) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(26, value);
+                ip = 2;
+            }
+            2 => {
+                // add [26], #-4 -> [26]
+                let value = checked!(i64::checked_add(load!(26), -4));
+                if !rt.store(26, value) {
+                    bail!();
+                }
+                ip = 6;
+            }
+            6 => {
+                // in -> [27]
+                if !rt.writable(27) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(27, value);
+                ip = 8;
+            }
+            8 => {
+                // mul [27], #2 -> [27]
+                let value = checked!(i64::checked_mul(load!(27), 2));
+                if !rt.store(27, value) {
+                    bail!();
+                }
+                ip = 12;
+            }
+            12 => {
+                // add [27], [26] -> [27]
+                let value = checked!(i64::checked_add(load!(27), load!(26)));
+                if !rt.store(27, value) {
+                    bail!();
+                }
+                ip = 16;
+            }
+            16 => {
+                // out [27]
+                let value = load!(27);
+                rt.write(ip, bp, value)?;
+                ip = 18;
+            }
+            18 => {
+                // add [28], #-1 -> [28]
+                let value = checked!(i64::checked_add(load!(28), -1));
+                if !rt.store(28, value) {
+                    bail!();
+                }
+                ip = 22;
+            }
+            22 => {
+                // jt [28], #6
+                if load!(28) != 0 {
+                    ip = 6;
+                    continue;
+                }
+                ip = 25;
+            }
+            25 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 57 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day7_b<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 52, 1001, 52, -5, 52, 3, 53, 1, 52, 56, 54, 1007, 54, 5, 55, 1005, 55, 26, 1001, 54, -5, 54, 1105, 1, 12, 1, 53, 54, 53, 1008, 54, 0, 55, 1001, 55, 1, 55, 2, 53, 55, 53, 4, 53, 1001, 56, -1, 56, 1005, 56, 6, 99, 0, 0, 0, 0, 10];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 4), (6, 2), (8, 4), (12, 4), (16, 3), (19, 4), (23, 3), (26, 4), (30, 4), (34, 4), (38, 4), (42, 2), (44, 4), (48, 3), (51, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [52]
+                if !rt.writable(52) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(52, value);
+                ip = 2;
+            }
+            2 => {
+                // add [52], #-5 -> [52]
+                let value = checked!(i64::checked_add(load!(52), -5));
+                if !rt.store(52, value) {
+                    bail!();
+                }
+                ip = 6;
+            }
+            6 => {
+                // in -> [53]
+                if !rt.writable(53) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(53, value);
+                ip = 8;
+            }
+            8 => {
+                // add [52], [56] -> [54]
+                let value = checked!(i64::checked_add(load!(52), load!(56)));
+                if !rt.store(54, value) {
+                    bail!();
+                }
+                ip = 12;
+            }
+            12 => {
+                // lt [54], #5 -> [55]
+                let value = (load!(54) < 5) as i64;
+                if !rt.store(55, value) {
+                    bail!();
+                }
+                ip = 16;
+            }
+            16 => {
+                // jt [55], #26
+                if load!(55) != 0 {
+                    ip = 26;
+                    continue;
+                }
+                ip = 19;
+            }
+            19 => {
+                // add [54], #-5 -> [54]
+                let value = checked!(i64::checked_add(load!(54), -5));
+                if !rt.store(54, value) {
+                    bail!();
+                }
+                ip = 23;
+            }
+            23 => {
+                // jt #1, #12
+                if 1 != 0 {
+                    ip = 12;
+                    continue;
+                }
+                ip = 26;
+            }
+            26 => {
+                // add [53], [54] -> [53]
+                let value = checked!(i64::checked_add(load!(53), load!(54)));
+                if !rt.store(53, value) {
+                    bail!();
+                }
+                ip = 30;
+            }
+            30 => {
+                // eq [54], #0 -> [55]
+                let value = (load!(54) == 0) as i64;
+                if !rt.store(55, value) {
+                    bail!();
+                }
+                ip = 34;
+            }
+            34 => {
+                // add [55], #1 -> [55]
+                let value = checked!(i64::checked_add(load!(55), 1));
+                if !rt.store(55, value) {
+                    bail!();
+                }
+                ip = 38;
+            }
+            38 => {
+                // mul [53], [55] -> [53]
+                let value = checked!(i64::checked_mul(load!(53), load!(55)));
+                if !rt.store(53, value) {
+                    bail!();
+                }
+                ip = 42;
+            }
+            42 => {
+                // out [53]
+                let value = load!(53);
+                rt.write(ip, bp, value)?;
+                ip = 44;
+            }
+            44 => {
+                // add [56], #-1 -> [56]
+                let value = checked!(i64::checked_add(load!(56), -1));
+                if !rt.store(56, value) {
+                    bail!();
+                }
+                ip = 48;
+            }
+            48 => {
+                // jt [56], #6
+                if load!(56) != 0 {
+                    ip = 6;
+                    continue;
+                }
+                ip = 51;
+            }
+            51 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 16 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day9_a<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 2), (4, 4), (8, 4), (12, 3), (15, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // arb #1
+                bp = checked!(i64::checked_add(bp, 1));
+                ip = 2;
+            }
+            2 => {
+                // out [rb-1]
+                let value = load!(checked!(i64::checked_add(bp, -1)));
+                rt.write(ip, bp, value)?;
+                ip = 4;
+            }
+            4 => {
+                // add [100], #1 -> [100]
+                let value = checked!(i64::checked_add(load!(100), 1));
+                if !rt.store(100, value) {
+                    bail!();
+                }
+                ip = 8;
+            }
+            8 => {
+                // eq [100], #16 -> [101]
+                let value = (load!(100) == 16) as i64;
+                if !rt.store(101, value) {
+                    bail!();
+                }
+                ip = 12;
+            }
+            12 => {
+                // jf [101], #0
+                if load!(101) == 0 {
+                    ip = 0;
+                    continue;
+                }
+                ip = 15;
+            }
+            15 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 8 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day9_b<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[1102, 34915192, 34915192, 7, 4, 7, 99, 0];
+    const ARMS: &[(i64, i64)] = &[(0, 4), (4, 2), (6, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // mul #34915192, #34915192 -> [7]
+                let value = checked!(i64::checked_mul(34915192, 34915192));
+                if !rt.store(7, value) {
+                    bail!();
+                }
+                ip = 4;
+            }
+            4 => {
+                // out [7]
+                let value = load!(7);
+                rt.write(ip, bp, value)?;
+                ip = 6;
+            }
+            6 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 3 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn day9_c<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[104, 1125899906842624, 99];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // out #1125899906842624
+                let value = 1125899906842624;
+                rt.write(ip, bp, value)?;
+                ip = 2;
+            }
+            2 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Transpiled from a 10 cell Intcode image.
+#[allow(clippy::all, unreachable_code, unused_macros, unused_mut)]
+pub fn overflow<IO: aoc::Io>(io: IO) -> Result<(aoc::Status, aoc::IntCode<IO>), aoc::IntcodeError> {
+    const IMAGE: &[i64] = &[3, 9, 1002, 9, 3, 9, 4, 9, 99, 0];
+    const ARMS: &[(i64, i64)] = &[(0, 2), (2, 4), (6, 2), (8, 1)];
+    let mut rt = aoc::transpile::Runtime::new(io, IMAGE, ARMS);
+    let mut ip: i64 = 0;
+    let mut bp: i64 = 0;
+    macro_rules! bail {
+        () => {{
+            match rt.interpret(ip, bp)? {
+                Some((next_ip, next_bp)) => {
+                    ip = next_ip;
+                    bp = next_bp;
+                    continue;
+                }
+                None => return Ok(rt.finish()),
+            }
+        }};
+    }
+    macro_rules! checked {
+        ($value:expr) => {
+            match $value {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    macro_rules! load {
+        ($addr:expr) => {
+            match rt.load($addr) {
+                Some(it) => it,
+                None => bail!(),
+            }
+        };
+    }
+    loop {
+        if !rt.is_live(ip) {
+            bail!();
+        }
+        match ip {
+            0 => {
+                // in -> [9]
+                if !rt.writable(9) {
+                    bail!();
+                }
+                let value = match rt.read(ip, bp)? {
+                    Some(it) => it,
+                    None => return Ok(rt.awaiting_input(ip, bp)),
+                };
+                rt.store(9, value);
+                ip = 2;
+            }
+            2 => {
+                // mul [9], #3 -> [9]
+                let value = checked!(i64::checked_mul(load!(9), 3));
+                if !rt.store(9, value) {
+                    bail!();
+                }
+                ip = 6;
+            }
+            6 => {
+                // out [9]
+                let value = load!(9);
+                rt.write(ip, bp, value)?;
+                ip = 8;
+            }
+            8 => {
+                // hlt
+                bail!();
+            }
+            _ => unreachable!(),
+        }
+    }
+}